@@ -0,0 +1,45 @@
+//! Counts the allocations made by log calls once the thread's line buffer has
+//! grown to fit, which should be none for a synchronous logger.
+//!
+//! Run with `cargo bench --bench allocations`.
+
+use log::info;
+use logout::{Clock, Output, OutputFormat, new_log};
+use stats_alloc::{INSTRUMENTED_SYSTEM, Region, StatsAlloc};
+use std::alloc::System;
+use std::error::Error;
+use std::io;
+
+#[global_allocator]
+static GLOBAL: &StatsAlloc<System> = &INSTRUMENTED_SYSTEM;
+
+const WARM_UP: u64 = 100;
+const CALLS: u64 = 100_000;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let _ = new_log()
+        .sink(io::sink())
+        .output(Output::new(io::sink()).output_format(OutputFormat::Json))
+        .output(
+            Output::new(io::sink())
+                .clock(Clock::Both)
+                .pattern("{time} {elapsed:>12} {level:>5} {target}: {message} {kv}")?,
+        )
+        .enable()?;
+
+    let log = |i: u64| info!(i, user = "alice smith"; "request {i} handled");
+    for i in 0..WARM_UP {
+        log(i);
+    }
+    let region = Region::new(GLOBAL);
+    for i in 0..CALLS {
+        log(i);
+    }
+    let stats = region.change();
+
+    println!(
+        "{CALLS} log calls to 3 sinks: {} allocations, {} reallocations, {} bytes allocated",
+        stats.allocations, stats.reallocations, stats.bytes_allocated,
+    );
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! Compares many threads logging at once to a file in append mode written
+//! under a lock, with the same file written without one.
+//!
+//! The file is `/dev/null`, so that only the cost of the system calls and of
+//! waiting for the lock is measured. Run with `cargo bench --bench contention`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use log::{Level, Log, Record};
+use logout::new_log;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::thread;
+
+const THREADS: usize = 16;
+const MESSAGES: usize = 1_000;
+
+fn dev_null() -> io::Result<File> {
+    OpenOptions::new().append(true).open("/dev/null")
+}
+
+fn log_from_threads(logger: &impl Log) {
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            let _ = scope.spawn(|| {
+                for i in 0..MESSAGES {
+                    logger.log(
+                        &Record::builder()
+                            .level(Level::Info)
+                            .target("bench")
+                            .args(format_args!("message {i}"))
+                            .build(),
+                    );
+                }
+            });
+        }
+    });
+}
+
+fn contention(c: &mut Criterion) {
+    let locked = new_log().sink(dev_null().expect("open /dev/null"));
+    let atomic = new_log().atomic_sink(dev_null().expect("open /dev/null"));
+    let mut group = c.benchmark_group(format!("contention/{THREADS}x{MESSAGES}"));
+    let _ = group.bench_function("locked", |b| b.iter(|| log_from_threads(&locked)));
+    let _ = group.bench_function("atomic", |b| b.iter(|| log_from_threads(&atomic)));
+    group.finish();
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
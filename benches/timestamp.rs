@@ -0,0 +1,43 @@
+//! Compares log calls using the cached time formats with log calls using the
+//! same layouts written as custom format descriptions, which are formatted in
+//! full for every message.
+//!
+//! Run with `cargo bench --bench timestamp`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use log::{Level, Log, Record};
+use logout::{TimeFormat, new_log};
+use std::io;
+
+const RFC2822: &str = "[weekday repr:short], [day] [month repr:short] [year] \
+    [hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]";
+const ISO8601_MICROS: &str = "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6]\
+    [offset_hour sign:mandatory]:[offset_minute]";
+
+fn log(logger: &impl Log) {
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("bench")
+            .args(format_args!("request handled"))
+            .build(),
+    );
+}
+
+fn timestamp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("timestamp");
+    for (name, time_format, description) in [
+        ("rfc2822", TimeFormat::Rfc2822, RFC2822),
+        ("iso8601_micros", TimeFormat::Iso8601Micros, ISO8601_MICROS),
+    ] {
+        let custom = TimeFormat::custom(description).expect("valid format description");
+        let cached = new_log().sink(io::sink()).time_format(time_format);
+        let uncached = new_log().sink(io::sink()).time_format(custom);
+        let _ = group.bench_function(format!("{name}/cached"), |b| b.iter(|| log(&cached)));
+        let _ = group.bench_function(format!("{name}/uncached"), |b| b.iter(|| log(&uncached)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, timestamp);
+criterion_main!(benches);
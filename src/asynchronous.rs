@@ -0,0 +1,303 @@
+//! Hand-off of formatted lines to a background writer thread.
+
+use crate::sink::{Sink, WriteErrors};
+use std::collections::VecDeque;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+/// What to do with a new message when the asynchronous queue is full.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait until the writer thread has made room.
+    #[default]
+    Block,
+    /// Discard the new message.
+    DropNewest,
+    /// Discard the oldest queued message to make room for the new one.
+    DropOldest,
+}
+
+/// Number of messages discarded because the asynchronous queue was full.
+///
+/// Obtained from `Logger::dropped_messages` and kept up to date after the
+/// logger is enabled.
+#[derive(Clone, Debug)]
+pub struct DroppedMessages(Arc<AtomicU64>);
+
+impl DroppedMessages {
+    /// Number of messages dropped so far.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A formatted line and the index of the sink it is destined for.
+#[derive(Debug)]
+pub(crate) struct Line {
+    pub(crate) sink: usize,
+    pub(crate) text: String,
+}
+
+/// Bounded queue of formatted lines waiting for the writer thread.
+#[derive(Debug)]
+pub(crate) struct Queue {
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    idle: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    lines: VecDeque<Line>,
+    // The writer has taken lines off the queue and not finished writing them.
+    busy: bool,
+    closed: bool,
+}
+
+impl Queue {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            idle: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub(crate) fn dropped(&self) -> DroppedMessages {
+        DroppedMessages(Arc::clone(&self.dropped))
+    }
+
+    /// Queue `line` for the writer, handing it back if the writer has been stopped.
+    pub(crate) fn push(&self, line: Line) -> Result<(), Line> {
+        let mut state = self.lock();
+        if state.lines.len() >= self.capacity && !state.closed {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    state = self
+                        .not_full
+                        .wait_while(state, |s| s.lines.len() >= self.capacity && !s.closed)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                OverflowPolicy::DropNewest => {
+                    let _ = self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                OverflowPolicy::DropOldest => {
+                    let _ = state.lines.pop_front();
+                    let _ = self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        if state.closed {
+            return Err(line);
+        }
+        state.lines.push_back(line);
+        drop(state);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Block until everything queued so far has been written.
+    pub(crate) fn wait_idle(&self) {
+        let state = self
+            .idle
+            .wait_while(self.lock(), |s| {
+                (!s.lines.is_empty() || s.busy) && !s.closed
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        drop(state);
+    }
+
+    /// Stop accepting lines. The writer exits once the queue is drained.
+    pub(crate) fn close(&self) {
+        self.lock().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    /// Wait for lines to be queued and take all of them. Returns `None` once
+    /// the queue is closed and drained.
+    fn pop_all(&self) -> Option<VecDeque<Line>> {
+        let mut state = self
+            .not_empty
+            .wait_while(self.lock(), |s| s.lines.is_empty() && !s.closed)
+            .unwrap_or_else(PoisonError::into_inner);
+        if state.lines.is_empty() {
+            state.busy = false;
+            drop(state);
+            self.idle.notify_all();
+            return None;
+        }
+        state.busy = true;
+        let batch = std::mem::take(&mut state.lines);
+        drop(state);
+        self.not_full.notify_all();
+        Some(batch)
+    }
+
+    fn done(&self) {
+        let mut state = self.lock();
+        state.busy = false;
+        let idle = state.lines.is_empty();
+        drop(state);
+        if idle {
+            self.idle.notify_all();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The thread draining a [`Queue`] into the sink.
+#[derive(Debug)]
+pub(crate) struct Writer {
+    queue: Arc<Queue>,
+    thread: JoinHandle<()>,
+}
+
+impl Writer {
+    /// Start the thread draining `queue` into `sinks`.
+    pub(crate) fn spawn(
+        queue: Arc<Queue>,
+        sinks: Vec<Sink>,
+        errors: Arc<WriteErrors>,
+    ) -> Result<Self, io::Error> {
+        let thread = {
+            let queue = Arc::clone(&queue);
+            thread::Builder::new()
+                .name("logout-writer".to_string())
+                .spawn(move || run(&queue, &sinks, &errors))?
+        };
+        Ok(Self { queue, thread })
+    }
+
+    /// Close the queue and wait for the thread to write out what is left.
+    pub(crate) fn stop(self) {
+        self.queue.close();
+        let _ = self.thread.join();
+    }
+}
+
+/// Write lines from `queue` until it is closed and drained. A sink that
+/// panics loses the line it was writing, but not the writer thread, unless
+/// failed writes are meant to panic. Then the queue is closed first, so that
+/// logging falls back to writing synchronously rather than waiting on a
+/// writer that is gone.
+fn run(queue: &Queue, sinks: &[Sink], errors: &WriteErrors) {
+    let mut panicked = false;
+    while let Some(batch) = queue.pop_all() {
+        for line in batch {
+            let sink = &sinks[line.sink];
+            let written =
+                panic::catch_unwind(AssertUnwindSafe(|| sink.write_line(&line.text, errors)));
+            if let Err(payload) = written {
+                if errors.panics() {
+                    queue.close();
+                    panic::resume_unwind(payload);
+                }
+                if !panicked {
+                    panicked = true;
+                    eprintln!(
+                        "a log sink panicked, discarding the line and continuing to log to it"
+                    );
+                }
+            }
+        }
+        queue.done();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WriteErrorPolicy;
+    use std::io::Write;
+
+    /// Panics on lines starting with `panic`, keeps the others.
+    struct Fragile(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Fragile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            assert!(!buf.starts_with(b"panic"), "sink failure");
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Fails every write.
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn line(text: &str) -> Line {
+        Line {
+            sink: 0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn writer_survives_panicking_sink() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let queue = Arc::new(Queue::new(1, OverflowPolicy::Block));
+        let writer = Writer::spawn(
+            Arc::clone(&queue),
+            vec![Sink::locked(Fragile(Arc::clone(&written)))],
+            Arc::new(WriteErrors::new(WriteErrorPolicy::Stderr)),
+        )
+        .unwrap();
+        for text in ["a\n", "panic\n", "b\n", "panic\n", "c\n"] {
+            queue.push(line(text)).unwrap();
+        }
+        queue.wait_idle();
+        assert_eq!(*written.lock().unwrap(), b"a\nb\nc\n");
+        writer.stop();
+    }
+
+    #[test]
+    fn panic_policy_closes_queue() {
+        let queue = Arc::new(Queue::new(4, OverflowPolicy::Block));
+        let writer = Writer::spawn(
+            Arc::clone(&queue),
+            vec![Sink::locked(Broken)],
+            Arc::new(WriteErrors::new(WriteErrorPolicy::Panic)),
+        )
+        .unwrap();
+        // Once the writer has died, lines are handed back to be written
+        // synchronously instead of blocking on the full queue.
+        let refused = (0..16)
+            .map(|i| queue.push(line(&format!("{i}\n"))))
+            .filter(Result::is_err)
+            .count();
+        assert!(refused > 0);
+        queue.wait_idle();
+        assert!(queue.push(line("last\n")).is_err());
+        assert!(writer.thread.join().is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! ANSI colors for terminal sinks.
+
+use log::Level;
+use std::any::Any;
+use std::env;
+use std::fs::File;
+use std::io::{IsTerminal, Stderr, Stdout};
+
+/// When to color the level and dim the time of text lines.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Always emit colors, even into files.
+    Always,
+    /// Never emit colors.
+    Never,
+    /// Emit colors when the sink is a terminal. `NO_COLOR` disables colors,
+    /// `CLICOLOR_FORCE` forces them even when the sink is not a terminal and
+    /// takes precedence over `NO_COLOR`.
+    #[default]
+    Auto,
+}
+
+/// A color mode resolved against the sink it applies to.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Color {
+    mode: ColorMode,
+    terminal: bool,
+    enabled: bool,
+}
+
+impl Color {
+    /// Default color mode for `sink`.
+    pub(crate) fn new<W: 'static>(sink: &W) -> Self {
+        let terminal = is_terminal(sink);
+        Self::resolve(ColorMode::default(), terminal)
+    }
+
+    #[must_use]
+    pub(crate) fn mode(self, mode: ColorMode) -> Self {
+        Self::resolve(mode, self.terminal)
+    }
+
+    /// The same color mode, applied to `sink`.
+    #[must_use]
+    pub(crate) fn sink<W: 'static>(self, sink: &W) -> Self {
+        Self::resolve(self.mode, is_terminal(sink))
+    }
+
+    pub(crate) fn enabled(self) -> bool {
+        self.enabled
+    }
+
+    fn resolve(mode: ColorMode, terminal: bool) -> Self {
+        let enabled = match mode {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                if env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+                    true
+                } else if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+                    || env::var_os("TERM").is_some_and(|t| t == "dumb")
+                {
+                    false
+                } else {
+                    terminal
+                }
+            }
+        };
+        Self {
+            mode,
+            terminal,
+            enabled,
+        }
+    }
+}
+
+/// Whether `sink` is known to be a terminal. Sinks of other types than
+/// stderr, stdout or a file are assumed not to be.
+fn is_terminal<W: 'static>(sink: &W) -> bool {
+    let sink: &dyn Any = sink;
+    if let Some(stderr) = sink.downcast_ref::<Stderr>() {
+        stderr.is_terminal()
+    } else if let Some(stdout) = sink.downcast_ref::<Stdout>() {
+        stdout.is_terminal()
+    } else if let Some(file) = sink.downcast_ref::<File>() {
+        file.is_terminal()
+    } else {
+        false
+    }
+}
+
+/// ANSI escape sequence starting the color for `level`.
+pub(crate) fn level_style(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[31m",
+        Level::Warn => "\x1b[33m",
+        Level::Info => "\x1b[32m",
+        Level::Debug => "\x1b[34m",
+        Level::Trace => "\x1b[35m",
+    }
+}
+
+pub(crate) const DIM: &str = "\x1b[2m";
+pub(crate) const RESET: &str = "\x1b[0m";
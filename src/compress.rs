@@ -0,0 +1,128 @@
+//! Background compression of rolled over log files.
+
+use crate::rotate::suffixed;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+/// Compression applied to log files once they have been rolled over.
+///
+/// Compression runs on a background thread so logging is not held up. The
+/// compressed file is written next to the original under a temporary name and
+/// only replaces it once complete.
+#[derive(Copy, Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Leave rolled over files as they are.
+    #[default]
+    None,
+    /// gzip, adding a `.gz` extension.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// zstd, adding a `.zst` extension.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Some("gz"),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Some("zst"),
+        }
+    }
+}
+
+/// Extensions of every compression, whether or not its feature is enabled, so
+/// that files compressed by an earlier run are recognised whatever the current
+/// compression.
+pub(crate) const EXTENSIONS: [&str; 2] = ["gz", "zst"];
+
+/// Compresses one file at a time on a background thread.
+#[derive(Debug)]
+pub(crate) struct Compressor {
+    compression: Compression,
+    pending: Option<JoinHandle<()>>,
+}
+
+impl Compressor {
+    pub(crate) fn new(compression: Compression) -> Self {
+        Self {
+            compression,
+            pending: None,
+        }
+    }
+
+    /// Extension added to compressed files, if any.
+    fn extension(&self) -> Option<&'static str> {
+        self.compression.extension()
+    }
+
+    /// Start compressing `src` into `src.<extension>`, first waiting for any
+    /// previous compression to finish.
+    pub(crate) fn spawn(&mut self, src: PathBuf) {
+        let Some(extension) = self.extension() else {
+            return;
+        };
+        self.wait();
+        let compression = self.compression;
+        let spawned = thread::Builder::new()
+            .name("logout-compress".to_string())
+            .spawn(move || {
+                let dst = suffixed(&src, extension);
+                if let Err(e) = compress(&src, &dst, compression) {
+                    eprintln!("error compressing {}: {e}", src.display());
+                }
+            });
+        match spawned {
+            Ok(handle) => self.pending = Some(handle),
+            Err(e) => eprintln!("error starting compression thread: {e}"),
+        }
+    }
+
+    /// Block until the compression in progress, if any, has finished.
+    pub(crate) fn wait(&mut self) {
+        if let Some(handle) = self.pending.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Compressor {
+    fn drop(&mut self) {
+        self.wait();
+    }
+}
+
+fn compress(src: &Path, dst: &Path, compression: Compression) -> Result<(), io::Error> {
+    let tmp = suffixed(dst, "tmp");
+    encode(compression, File::open(src)?, File::create(&tmp)?)?.sync_all()?;
+    fs::rename(&tmp, dst)?;
+    fs::remove_file(src)
+}
+
+fn encode(compression: Compression, mut input: File, output: File) -> Result<File, io::Error> {
+    match compression {
+        Compression::None => {
+            let mut output = output;
+            let _ = io::copy(&mut input, &mut output)?;
+            Ok(output)
+        }
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            let _ = io::copy(&mut input, &mut encoder)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let mut encoder = zstd::Encoder::new(output, 0)?;
+            let _ = io::copy(&mut input, &mut encoder)?;
+            encoder.finish()
+        }
+    }
+}
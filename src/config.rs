@@ -0,0 +1,905 @@
+//! Logging configuration read from a TOML file.
+
+use crate::color::ColorMode;
+use crate::filter::{Filter, SharedFilter};
+use crate::format::OutputFormat;
+use crate::logout::{LogHandle, Logger, new_log};
+use crate::output::Output;
+use crate::pattern::Pattern;
+use crate::timestamp::{Clock, TimeFormat, Timezone};
+use log::{LevelFilter, SetLoggerError};
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use time::UtcOffset;
+use time::format_description;
+use toml::{Table, Value};
+
+/// Keys that are only read when the logger is enabled.
+const LAYOUT_KEYS: [&str; 7] = [
+    "file",
+    "stream",
+    "format",
+    "pattern",
+    "time_format",
+    "clock",
+    "color",
+];
+
+/// Error returned when a [`LogConfig`] cannot be loaded or enabled.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The configuration file, or a log file it names, cannot be opened.
+    Io { path: PathBuf, source: io::Error },
+    /// The configuration is not valid TOML.
+    Syntax { message: String },
+    /// The value of `key` is invalid, or `key` is not known.
+    Invalid { key: String, message: String },
+    /// The configuration was parsed from a string, so there is no file to watch.
+    NotLoaded,
+    /// The thread watching the configuration file cannot be started.
+    Watch(io::Error),
+    /// A logger is already installed.
+    SetLogger(SetLoggerError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "cannot open `{}`: {source}", path.display()),
+            Self::Syntax { message } => write!(f, "invalid logging configuration: {message}"),
+            Self::Invalid { key, message } => write!(f, "invalid `{key}`: {message}"),
+            Self::NotLoaded => f.write_str("the logging configuration was not loaded from a file"),
+            Self::Watch(e) => write!(f, "error starting the configuration watcher thread: {e}"),
+            Self::SetLogger(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Watch(e) => Some(e),
+            Self::SetLogger(e) => Some(e),
+            Self::Syntax { .. } | Self::Invalid { .. } | Self::NotLoaded => None,
+        }
+    }
+}
+
+/// A logger configuration read from a TOML file.
+///
+/// The top level of the file configures the logger's own sink, each
+/// `[[outputs]]` table an additional [`Output`]. Every key is optional,
+/// except that an output needs a `file` or a `stream`.
+///
+/// ```toml
+/// timezone = "utc"             # `local` (the default), `utc` or an offset such as `+02:00`
+///
+/// stream = "stderr"            # `stderr` (the default) or `stdout`
+/// file = "app.log"             # append to a file instead
+/// level = "info"               # `off`, `error`, `warn`, `info`, `debug` or `trace`
+/// filters = "my_crate=debug"   # `RUST_LOG` style directives, applied after `level`
+/// format = "text"              # `text` or `json`
+/// pattern = "{time} {level:>5} {target}: {message}"
+/// time_format = "rfc3339"      # see below
+/// clock = "wall-clock"         # `wall-clock`, `elapsed` or `both`
+/// color = "auto"               # `auto`, `always` or `never`
+///
+/// [targets]                    # per-target levels, applied after `filters`
+/// hyper = "warn"
+/// "hyper::proto" = "off"
+///
+/// [[outputs]]
+/// file = "debug.jsonl"
+/// level = "debug"
+/// format = "json"
+/// ```
+///
+/// `time_format` is one of `rfc2822`, `rfc3339`, `iso8601-millis`,
+/// `iso8601-micros`, `unix-seconds` or `unix-nanos`, or a custom format
+/// description such as `"[hour]:[minute]:[second]"`, see [`TimeFormat::custom`].
+///
+/// # Reloading
+///
+/// [`LogConfig::watch`] polls the file for changes. The levels of a changed
+/// file, `level`, `filters` and `targets`, are applied right away; outputs are
+/// matched by position. Sinks, formats and the time zone are only read at
+/// startup, so changing them logs a warning that a restart is needed. A file
+/// that cannot be read or is invalid is rejected as a whole: the error is
+/// logged and the previous levels are kept.
+///
+/// # Examples
+///
+/// Configure the logger from a file and apply its levels whenever it changes.
+/// ```rust
+/// use logout::LogConfig;
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let config_path = std::env::temp_dir().join("logout-doctest-config.toml");
+/// #   std::fs::write(&config_path, "level = \"info\"\n[targets]\nhyper = \"warn\"\n")?;
+///     let config = LogConfig::load(&config_path)?;
+///     let handle = config.enable()?;
+///     // The file is polled until `_watcher` goes out of scope.
+///     let _watcher = config.watch(handle, Duration::from_secs(5))?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    path: Option<PathBuf>,
+    version: Option<Version>,
+    timezone: Timezone,
+    primary: SinkConfig,
+    outputs: Vec<SinkConfig>,
+}
+
+/// When the configuration file was last changed, as far as polling can tell.
+type Version = (SystemTime, u64);
+
+#[derive(Clone, Debug)]
+struct SinkConfig {
+    destination: Destination,
+    filter: Filter,
+    output_format: Option<OutputFormat>,
+    pattern: Option<Pattern>,
+    time_format: Option<TimeFormat>,
+    clock: Option<Clock>,
+    color: Option<ColorMode>,
+    // The values of `LAYOUT_KEYS`, to tell whether a reload needs a restart.
+    layout: Table,
+}
+
+#[derive(Clone, Debug)]
+enum Destination {
+    Stderr,
+    Stdout,
+    File(PathBuf),
+}
+
+impl LogConfig {
+    /// Read the configuration from the TOML file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or the configuration is
+    /// invalid.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let io_error = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        // Taken before reading, so a change made while reading is not missed.
+        let version = version(path).map_err(io_error)?;
+        let text = fs::read_to_string(path).map_err(io_error)?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            version: Some(version),
+            ..Self::parse(&text)?
+        })
+    }
+
+    /// Read the configuration from the TOML document `text`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut table: Table = text.parse().map_err(|e: toml::de::Error| {
+            // On a single line, unlike the `Display` of `e`, to be logged.
+            let line = e
+                .span()
+                .and_then(|span| text.get(..span.start))
+                .map_or(1, |before| before.matches('\n').count() + 1);
+            ConfigError::Syntax {
+                message: format!("line {line}: {}", e.message()),
+            }
+        })?;
+        let timezone = match table.remove("timezone") {
+            Some(value) => parse_timezone(as_str("timezone", &value)?)?,
+            None => Timezone::Local,
+        };
+        let outputs = match table.remove("outputs") {
+            Some(Value::Array(outputs)) => outputs
+                .iter()
+                .enumerate()
+                .map(|(i, output)| {
+                    let key = format!("outputs[{i}]");
+                    match output {
+                        Value::Table(output) => SinkConfig::parse(&key, output, false),
+                        _ => Err(invalid(&key, "expected a table")),
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(invalid(
+                    "outputs",
+                    "expected an array of tables, written `[[outputs]]`",
+                ));
+            }
+            None => Vec::new(),
+        };
+        Ok(Self {
+            path: None,
+            version: None,
+            timezone,
+            primary: SinkConfig::parse("", &table, true)?,
+            outputs,
+        })
+    }
+
+    /// Install a logger configured accordingly, see `Logger::enable`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a log file cannot be opened or a logger is already
+    /// installed.
+    pub fn enable(&self) -> Result<LogHandle, ConfigError> {
+        let mut logger = new_log().timezone(self.timezone);
+        for output in &self.outputs {
+            logger = logger.output(output.output()?);
+        }
+        match &self.primary.destination {
+            Destination::Stderr => self.primary.configure(logger).enable(),
+            Destination::Stdout => self.primary.configure(logger.sink(io::stdout())).enable(),
+            Destination::File(path) => {
+                let logger = logger.to_file(path).map_err(|source| ConfigError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                self.primary.configure(logger).enable()
+            }
+        }
+        .map_err(ConfigError::SetLogger)
+    }
+
+    /// Poll the file the configuration was loaded from every `interval`, and
+    /// apply the levels of each valid change through `handle`, see
+    /// [reloading](LogConfig#reloading). The file is watched until the returned
+    /// watcher is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration was not loaded from a file or the
+    /// watcher thread cannot be started.
+    pub fn watch(
+        &self,
+        handle: LogHandle,
+        interval: Duration,
+    ) -> Result<ConfigWatcher, ConfigError> {
+        let path = self.path.clone().ok_or(ConfigError::NotLoaded)?;
+        let mut watched = Watched {
+            path,
+            version: self.version,
+            running: self.clone(),
+            handle,
+        };
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("logout-config".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    watched.poll();
+                }
+            })
+            .map_err(ConfigError::Watch)?;
+        Ok(ConfigWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    /// Whether enabling `self` rather than `other` would lay out or send lines
+    /// differently.
+    fn layout_differs(&self, other: &Self) -> bool {
+        self.timezone != other.timezone
+            || self.primary.layout != other.primary.layout
+            || self.outputs.len() != other.outputs.len()
+            || self
+                .outputs
+                .iter()
+                .zip(&other.outputs)
+                .any(|(output, other)| output.layout != other.layout)
+    }
+}
+
+impl SinkConfig {
+    /// Parse the sink described by `table`, whose keys are reported prefixed
+    /// with `prefix`. The primary sink defaults to stderr, outputs need a
+    /// destination.
+    fn parse(prefix: &str, table: &Table, primary: bool) -> Result<Self, ConfigError> {
+        let keys = Keys { prefix, table };
+        if let Some(name) = table.keys().find(|name| {
+            !LAYOUT_KEYS.contains(&name.as_str())
+                && !["level", "filters", "targets"].contains(&name.as_str())
+        }) {
+            return Err(invalid(&keys.key(name), "unknown key"));
+        }
+
+        let destination = match (keys.str("file")?, keys.str("stream")?) {
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    &keys.key("file"),
+                    "cannot be combined with `stream`",
+                ));
+            }
+            (Some(file), None) => Destination::File(PathBuf::from(file)),
+            (None, Some(_)) => keys
+                .choice(
+                    "stream",
+                    "stream",
+                    &[
+                        ("stderr", Destination::Stderr),
+                        ("stdout", Destination::Stdout),
+                    ],
+                )?
+                .unwrap_or(Destination::Stderr),
+            (None, None) if primary => Destination::Stderr,
+            (None, None) => return Err(invalid(prefix, "expected a `file` or a `stream`")),
+        };
+        let output_format = keys.choice(
+            "format",
+            "format",
+            &[("text", OutputFormat::Text), ("json", OutputFormat::Json)],
+        )?;
+        let pattern = keys
+            .str("pattern")?
+            .map(|pattern| Pattern::parse(pattern).map_err(|e| invalid(&keys.key("pattern"), e)))
+            .transpose()?;
+        if pattern.is_some() && matches!(output_format, Some(OutputFormat::Json)) {
+            return Err(invalid(
+                &keys.key("pattern"),
+                "cannot be combined with `format = \"json\"`",
+            ));
+        }
+        let time_format = keys
+            .str("time_format")?
+            .map(|time_format| parse_time_format(&keys.key("time_format"), time_format))
+            .transpose()?;
+
+        Ok(Self {
+            destination,
+            filter: keys.filter()?,
+            output_format,
+            pattern,
+            time_format,
+            clock: keys.choice(
+                "clock",
+                "clock",
+                &[
+                    ("wall-clock", Clock::WallClock),
+                    ("elapsed", Clock::Elapsed),
+                    ("both", Clock::Both),
+                ],
+            )?,
+            color: keys.choice(
+                "color",
+                "color mode",
+                &[
+                    ("auto", ColorMode::Auto),
+                    ("always", ColorMode::Always),
+                    ("never", ColorMode::Never),
+                ],
+            )?,
+            layout: table
+                .iter()
+                .filter(|(name, _)| LAYOUT_KEYS.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        })
+    }
+
+    fn configure<T: Write + Send + 'static>(&self, mut logger: Logger<T>) -> Logger<T> {
+        if let Some(time_format) = &self.time_format {
+            logger = logger.time_format(time_format.clone());
+        }
+        if let Some(clock) = self.clock {
+            logger = logger.clock(clock);
+        }
+        if let Some(color) = self.color {
+            logger = logger.color(color);
+        }
+        if let Some(output_format) = self.output_format {
+            logger = logger.output_format(output_format);
+        }
+        if let Some(pattern) = &self.pattern {
+            logger = logger.formatter(pattern.clone());
+        }
+        logger.filter(self.filter.clone())
+    }
+
+    fn output(&self) -> Result<Output, ConfigError> {
+        let mut output = match &self.destination {
+            Destination::Stderr => Output::new(io::stderr()),
+            Destination::Stdout => Output::new(io::stdout()),
+            Destination::File(path) => Output::file(path).map_err(|source| ConfigError::Io {
+                path: path.clone(),
+                source,
+            })?,
+        };
+        if let Some(time_format) = &self.time_format {
+            output = output.time_format(time_format.clone());
+        }
+        if let Some(clock) = self.clock {
+            output = output.clock(clock);
+        }
+        if let Some(color) = self.color {
+            output = output.color(color);
+        }
+        if let Some(output_format) = self.output_format {
+            output = output.output_format(output_format);
+        }
+        if let Some(pattern) = &self.pattern {
+            output = output.formatter(pattern.clone());
+        }
+        output.filter = SharedFilter::new(self.filter.clone());
+        Ok(output)
+    }
+}
+
+/// Watches a configuration file, see [`LogConfig::watch`]. The file is no
+/// longer watched once the watcher is dropped.
+#[must_use = "the file is no longer watched once the watcher is dropped"]
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the thread up.
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            eprintln!("the configuration watcher thread panicked");
+        }
+    }
+}
+
+/// State of the watcher thread.
+struct Watched {
+    path: PathBuf,
+    version: Option<Version>,
+    // The configuration the logger was enabled with.
+    running: LogConfig,
+    handle: LogHandle,
+}
+
+impl Watched {
+    fn poll(&mut self) {
+        let version = version(&self.path).ok();
+        if version == self.version {
+            return;
+        }
+        self.version = version;
+        let config = match LogConfig::load(&self.path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!(
+                    "cannot reload the logging configuration from `{}`, keeping the previous one: {e}",
+                    self.path.display()
+                );
+                return;
+            }
+        };
+        self.handle.replace_filters(
+            config.primary.filter.clone(),
+            config
+                .outputs
+                .iter()
+                .map(|output| output.filter.clone())
+                .collect(),
+        );
+        if config.layout_differs(&self.running) {
+            log::warn!(
+                "reloaded the levels from `{}`, changes to sinks, formats and the time zone take effect after a restart",
+                self.path.display()
+            );
+        } else {
+            log::info!(
+                "reloaded the logging configuration from `{}`",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// The keys of a sink table, named in errors after the table they are in.
+struct Keys<'a> {
+    prefix: &'a str,
+    table: &'a Table,
+}
+
+impl<'a> Keys<'a> {
+    fn key(&self, name: &str) -> String {
+        match self.prefix {
+            "" => name.to_string(),
+            prefix => format!("{prefix}.{name}"),
+        }
+    }
+
+    fn str(&self, name: &str) -> Result<Option<&'a str>, ConfigError> {
+        self.table
+            .get(name)
+            .map(|value| as_str(&self.key(name), value))
+            .transpose()
+    }
+
+    /// The value of `name`, one of the names of `choices`.
+    fn choice<T: Clone>(
+        &self,
+        name: &str,
+        what: &str,
+        choices: &[(&str, T)],
+    ) -> Result<Option<T>, ConfigError> {
+        let Some(value) = self.str(name)? else {
+            return Ok(None);
+        };
+        let Some((_, choice)) = choices.iter().find(|(choice, _)| *choice == value) else {
+            let mut expected = String::new();
+            for (i, (choice, _)) in choices.iter().enumerate() {
+                let separator = match i {
+                    0 => "",
+                    i if i + 1 == choices.len() => " or ",
+                    _ => ", ",
+                };
+                let _ = write!(expected, "{separator}`{choice}`");
+            }
+            return Err(invalid(
+                &self.key(name),
+                format!("unknown {what} `{value}`, expected {expected}"),
+            ));
+        };
+        Ok(Some(choice.clone()))
+    }
+
+    /// The levels set by `level`, then `filters`, then `targets`.
+    fn filter(&self) -> Result<Filter, ConfigError> {
+        let mut filter = Filter::new(match self.str("level")? {
+            Some(level) => parse_level(&self.key("level"), level)?,
+            None => LevelFilter::Info,
+        });
+        if let Some(filters) = self.str("filters")? {
+            filter
+                .parse(filters)
+                .map_err(|e| invalid(&self.key("filters"), e))?;
+        }
+        match self.table.get("targets") {
+            Some(Value::Table(targets)) => {
+                for (target, level) in targets {
+                    let key = format!("{}.\"{target}\"", self.key("targets"));
+                    let level = parse_level(&key, as_str(&key, level)?)?;
+                    filter.add_directive(target.clone(), level);
+                }
+            }
+            Some(_) => return Err(invalid(&self.key("targets"), "expected a table")),
+            None => {}
+        }
+        Ok(filter)
+    }
+}
+
+fn version(path: &Path) -> io::Result<Version> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+fn invalid(key: &str, message: impl fmt::Display) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn as_str<'a>(key: &str, value: &'a Value) -> Result<&'a str, ConfigError> {
+    value.as_str().ok_or_else(|| {
+        invalid(
+            key,
+            format!("expected a string, found {}", value.type_str()),
+        )
+    })
+}
+
+fn parse_level(key: &str, level: &str) -> Result<LevelFilter, ConfigError> {
+    LevelFilter::from_str(level).map_err(|_| {
+        invalid(
+            key,
+            format!(
+                "unknown level `{level}`, expected `off`, `error`, `warn`, `info`, `debug` or `trace`"
+            ),
+        )
+    })
+}
+
+fn parse_time_format(key: &str, time_format: &str) -> Result<TimeFormat, ConfigError> {
+    Ok(match time_format {
+        "rfc2822" => TimeFormat::Rfc2822,
+        "rfc3339" => TimeFormat::Rfc3339,
+        "iso8601-millis" => TimeFormat::Iso8601Millis,
+        "iso8601-micros" => TimeFormat::Iso8601Micros,
+        "unix-seconds" => TimeFormat::UnixSeconds,
+        "unix-nanos" => TimeFormat::UnixNanos,
+        // Any meaningful description has a component in brackets.
+        description if description.contains('[') => {
+            TimeFormat::custom(description).map_err(|e| invalid(key, e))?
+        }
+        _ => {
+            return Err(invalid(
+                key,
+                format!(
+                    "unknown time format `{time_format}`, expected `rfc2822`, `rfc3339`, \
+                     `iso8601-millis`, `iso8601-micros`, `unix-seconds`, `unix-nanos` or a \
+                     format description such as `[hour]:[minute]:[second]`"
+                ),
+            ));
+        }
+    })
+}
+
+fn parse_timezone(timezone: &str) -> Result<Timezone, ConfigError> {
+    match timezone {
+        "local" => Ok(Timezone::Local),
+        "utc" => Ok(Timezone::Utc),
+        offset => format_description::parse_borrowed::<2>("[offset_hour sign:mandatory]:[offset_minute]")
+            .ok()
+            .and_then(|description| UtcOffset::parse(offset, &description).ok())
+            .map(Timezone::Fixed)
+            .ok_or_else(|| {
+                invalid(
+                    "timezone",
+                    format!("unknown time zone `{timezone}`, expected `local`, `utc` or an offset such as `+02:00`"),
+                )
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::{Level, Log, Metadata};
+
+    fn metadata(level: Level, target: &str) -> Metadata<'_> {
+        Metadata::builder().level(level).target(target).build()
+    }
+
+    /// The key `text` is rejected for.
+    fn rejected(text: &str) -> String {
+        match LogConfig::parse(text) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("{text}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_every_key() {
+        let config = LogConfig::parse(
+            r#"
+            timezone = "-03:30"
+            file = "app.log"
+            level = "warn"
+            filters = "my_crate=debug"
+            pattern = "{level} {message}"
+            time_format = "[hour]:[minute]"
+            clock = "both"
+            color = "never"
+
+            [targets]
+            "my_crate::db" = "trace"
+
+            [[outputs]]
+            stream = "stdout"
+            format = "json"
+            time_format = "unix-nanos"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.timezone,
+            Timezone::Fixed(UtcOffset::from_hms(-3, -30, 0).unwrap())
+        );
+        let primary = &config.primary;
+        assert!(
+            matches!(&primary.destination, Destination::File(path) if path == Path::new("app.log"))
+        );
+        assert!(!primary.filter.enabled(&metadata(Level::Info, "app")));
+        assert!(primary.filter.enabled(&metadata(Level::Debug, "my_crate")));
+        assert!(
+            primary
+                .filter
+                .enabled(&metadata(Level::Trace, "my_crate::db"))
+        );
+        assert!(primary.pattern.is_some());
+        assert!(matches!(primary.time_format, Some(TimeFormat::Custom(_))));
+        assert_eq!(primary.clock, Some(Clock::Both));
+        assert_eq!(primary.color, Some(ColorMode::Never));
+
+        let [output] = config.outputs.as_slice() else {
+            panic!("{:?}", config.outputs);
+        };
+        assert!(matches!(output.destination, Destination::Stdout));
+        assert!(matches!(output.output_format, Some(OutputFormat::Json)));
+        assert!(matches!(output.time_format, Some(TimeFormat::UnixNanos)));
+        assert!(output.filter.enabled(&metadata(Level::Info, "app")));
+    }
+
+    #[test]
+    fn defaults_to_stderr_at_info() {
+        let config = LogConfig::parse("").unwrap();
+        assert_eq!(config.timezone, Timezone::Local);
+        assert!(matches!(config.primary.destination, Destination::Stderr));
+        assert!(config.primary.filter.enabled(&metadata(Level::Info, "app")));
+        assert!(
+            !config
+                .primary
+                .filter
+                .enabled(&metadata(Level::Debug, "app"))
+        );
+        assert!(config.outputs.is_empty());
+    }
+
+    #[test]
+    fn parses_time_zones_and_time_formats() {
+        for (timezone, expected) in [
+            ("local", Timezone::Local),
+            ("utc", Timezone::Utc),
+            (
+                "+05:45",
+                Timezone::Fixed(UtcOffset::from_hms(5, 45, 0).unwrap()),
+            ),
+            (
+                "-12:00",
+                Timezone::Fixed(UtcOffset::from_hms(-12, 0, 0).unwrap()),
+            ),
+        ] {
+            let config = LogConfig::parse(&format!("timezone = \"{timezone}\"")).unwrap();
+            assert_eq!(config.timezone, expected, "{timezone}");
+        }
+        for time_format in [
+            "rfc2822",
+            "rfc3339",
+            "iso8601-millis",
+            "iso8601-micros",
+            "unix-seconds",
+            "unix-nanos",
+            "[year]-[month]",
+        ] {
+            let config = LogConfig::parse(&format!("time_format = \"{time_format}\"")).unwrap();
+            assert!(config.primary.time_format.is_some(), "{time_format}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for (text, key) in [
+            ("colour = \"never\"", "colour"),
+            (
+                "[[outputs]]\nstream = \"stderr\"\nlevle = \"info\"",
+                "outputs[0].levle",
+            ),
+            ("file = \"app.log\"\nstream = \"stderr\"", "file"),
+            ("stream = \"stdin\"", "stream"),
+            ("[[outputs]]\nlevel = \"info\"", "outputs[0]"),
+            ("outputs = \"stderr\"", "outputs"),
+            ("format = \"json\"\npattern = \"{message}\"", "pattern"),
+            (
+                "[[outputs]]\nstream = \"stderr\"\nformat = \"json\"\npattern = \"{message}\"",
+                "outputs[0].pattern",
+            ),
+            ("pattern = \"{message\"", "pattern"),
+            ("timezone = \"+2\"", "timezone"),
+            ("timezone = \"Europe/Paris\"", "timezone"),
+            ("timezone = 2", "timezone"),
+            ("time_format = \"fast\"", "time_format"),
+            ("time_format = \"[nope]\"", "time_format"),
+            ("level = \"loud\"", "level"),
+            ("filters = \"=debug\"", "filters"),
+            ("[targets]\nhyper = \"loud\"", "targets.\"hyper\""),
+            ("targets = \"hyper=warn\"", "targets"),
+            ("color = \"sometimes\"", "color"),
+        ] {
+            assert_eq!(rejected(text), key, "{text}");
+        }
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_line() {
+        match LogConfig::parse("level = \"info\"\nfilters = ") {
+            Err(ConfigError::Syntax { message }) => {
+                assert!(message.starts_with("line 2:"), "{message}");
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn only_layout_changes_differ() {
+        let running = LogConfig::parse("level = \"info\"\nformat = \"text\"").unwrap();
+        let differs = |text| LogConfig::parse(text).unwrap().layout_differs(&running);
+        assert!(!differs(
+            "level = \"debug\"\nformat = \"text\"\n[targets]\nhyper = \"off\""
+        ));
+        assert!(differs("level = \"info\"\nformat = \"json\""));
+        assert!(differs("format = \"text\"\ntimezone = \"utc\""));
+        assert!(differs(
+            "format = \"text\"\n[[outputs]]\nstream = \"stdout\""
+        ));
+    }
+
+    /// A watcher of the configuration in `dir`, initially `text`, applying it
+    /// through a clone of the handle of `logger`, as `LogGuard::handle` gives.
+    fn watched(dir: &Path, text: &str) -> (Watched, Logger<io::Stderr>) {
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("log.toml");
+        fs::write(&path, text).unwrap();
+        let config = LogConfig::load(&path).unwrap();
+        let mut logger = new_log();
+        for output in &config.outputs {
+            logger = logger.output(output.output().unwrap());
+        }
+        let logger = config.primary.configure(logger);
+        let watched = Watched {
+            path,
+            version: config.version,
+            running: config.clone(),
+            handle: logger.handle().clone(),
+        };
+        (watched, logger)
+    }
+
+    #[test]
+    fn reload_applies_valid_changes_and_keeps_the_rest() {
+        let dir = std::env::temp_dir().join(format!("logout-{}-config-reload", std::process::id()));
+        let (mut watched, logger) = watched(&dir, "level = \"info\"\n");
+        let enabled = |level| Log::enabled(&logger, &metadata(level, "app"));
+        assert!(!enabled(Level::Debug));
+
+        fs::write(&watched.path, "level = \"debug\"\n").unwrap();
+        watched.poll();
+        assert!(enabled(Level::Debug));
+
+        // Rejected as a whole, the valid `level` included.
+        fs::write(&watched.path, "level = \"trace\"\ncolour = \"never\"\n").unwrap();
+        watched.poll();
+        assert!(!enabled(Level::Trace));
+        assert!(enabled(Level::Debug));
+
+        fs::write(&watched.path, "level = \"oops\n").unwrap();
+        watched.poll();
+        assert!(enabled(Level::Debug));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reload_changes_outputs_by_position() {
+        let dir =
+            std::env::temp_dir().join(format!("logout-{}-config-outputs", std::process::id()));
+        let (mut watched, logger) = watched(
+            &dir,
+            "level = \"off\"\n[[outputs]]\nstream = \"stderr\"\nlevel = \"info\"\n",
+        );
+        let enabled = |level| Log::enabled(&logger, &metadata(level, "app"));
+        assert!(enabled(Level::Info));
+        assert!(!enabled(Level::Trace));
+
+        fs::write(
+            &watched.path,
+            "level = \"off\"\n[[outputs]]\nstream = \"stderr\"\nlevel = \"trace\"\n",
+        )
+        .unwrap();
+        watched.poll();
+        assert!(enabled(Level::Trace));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+//! Level filtering by log target.
+//!
+//! A [`Filter`] holds a default level plus any number of per-target
+//! overrides. A target override applies to the target itself and to every
+//! module below it, so `hyper` matches `hyper` and `hyper::proto::h1` but not
+//! `hyperlocal`. When several overrides match, the longest one wins.
+//!
+//! Filters can also be described with the familiar `RUST_LOG` syntax: a comma
+//! separated list of directives, each either a bare level (`info`), a bare
+//! target (`my_crate`, enabling every level for it) or `target=level`
+//! (`hyper::proto=off`).
+
+use log::{LevelFilter, Metadata};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Error returned when a filter directive string cannot be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterError {
+    /// The level in `directive` is not one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+    InvalidLevel { directive: String },
+    /// The target in `directive` is empty or contains whitespace.
+    InvalidTarget { directive: String },
+    /// `directive` contains more than one `=`.
+    Malformed { directive: String },
+    /// The environment variable `var` does not contain valid unicode.
+    NotUnicode { var: String },
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLevel { directive } => {
+                write!(f, "invalid log level in filter directive `{directive}`")
+            }
+            Self::InvalidTarget { directive } => {
+                write!(f, "invalid target in filter directive `{directive}`")
+            }
+            Self::Malformed { directive } => {
+                write!(f, "malformed filter directive `{directive}`")
+            }
+            Self::NotUnicode { var } => {
+                write!(f, "environment variable `{var}` is not valid unicode")
+            }
+        }
+    }
+}
+
+impl Error for FilterError {}
+
+#[derive(Clone, Debug)]
+struct Directive {
+    target: String,
+    level: LevelFilter,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Filter {
+    level: LevelFilter,
+    // Kept sorted by descending target length so the first match is the most specific.
+    directives: Vec<Directive>,
+}
+
+impl Filter {
+    pub(crate) fn new(level: LevelFilter) -> Self {
+        Self {
+            level,
+            directives: Vec::new(),
+        }
+    }
+
+    pub(crate) fn set_level(&mut self, level: LevelFilter) {
+        self.level = level;
+    }
+
+    /// Adds an override for `target`, replacing any previous override for the same target.
+    pub(crate) fn add_directive(&mut self, target: String, level: LevelFilter) {
+        self.directives.retain(|d| d.target != target);
+        let pos = self
+            .directives
+            .iter()
+            .position(|d| d.target.len() < target.len())
+            .unwrap_or(self.directives.len());
+        self.directives.insert(pos, Directive { target, level });
+    }
+
+    /// Removes the override for `target`, if any.
+    pub(crate) fn remove_directive(&mut self, target: &str) {
+        self.directives.retain(|d| d.target != target);
+    }
+
+    /// Level that applies to records logged with `target`.
+    pub(crate) fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|d| matches(&d.target, target))
+            .map_or(self.level, |d| d.level)
+    }
+
+    pub(crate) fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    /// Applies a `RUST_LOG` style directive string. Nothing is applied if any
+    /// directive fails to parse.
+    pub(crate) fn parse(&mut self, spec: &str) -> Result<(), FilterError> {
+        let mut level = None;
+        let mut directives = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let mut parts = directive.split('=');
+            let (target, target_level) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(token), None, None) => match LevelFilter::from_str(token) {
+                    Ok(l) => {
+                        level = Some(l);
+                        continue;
+                    }
+                    Err(_) => (token, LevelFilter::Trace),
+                },
+                (Some(target), Some(l), None) => {
+                    let l =
+                        LevelFilter::from_str(l.trim()).map_err(|_| FilterError::InvalidLevel {
+                            directive: directive.to_string(),
+                        })?;
+                    (target.trim(), l)
+                }
+                _ => {
+                    return Err(FilterError::Malformed {
+                        directive: directive.to_string(),
+                    });
+                }
+            };
+            if target.is_empty() || target.contains(char::is_whitespace) {
+                return Err(FilterError::InvalidTarget {
+                    directive: directive.to_string(),
+                });
+            }
+            directives.push((target.to_string(), target_level));
+        }
+
+        if let Some(level) = level {
+            self.set_level(level);
+        }
+        for (target, level) in directives {
+            self.add_directive(target, level);
+        }
+        Ok(())
+    }
+
+    /// The most verbose level any record could be logged at.
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|d| d.level)
+            .fold(self.level, Ord::max)
+    }
+}
+
+/// A filter that can be changed while the logger is in use.
+#[derive(Debug)]
+pub(crate) struct SharedFilter(Arc<RwLock<Filter>>);
+
+impl SharedFilter {
+    pub(crate) fn new(filter: Filter) -> Self {
+        Self(Arc::new(RwLock::new(filter)))
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, Filter> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, Filter> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Another reference to the same filter.
+    pub(crate) fn share(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl Clone for SharedFilter {
+    /// A copy of the filter, changed independently of this one.
+    fn clone(&self) -> Self {
+        Self::new(self.read().clone())
+    }
+}
+
+fn matches(prefix: &str, target: &str) -> bool {
+    match target.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn parsed(spec: &str) -> Result<Filter, FilterError> {
+        let mut filter = Filter::new(LevelFilter::Info);
+        filter.parse(spec)?;
+        Ok(filter)
+    }
+
+    fn error(spec: &str) -> FilterError {
+        parsed(spec).expect_err(spec)
+    }
+
+    #[test]
+    fn rejects_malformed_directives() {
+        assert_eq!(
+            error("=debug"),
+            FilterError::InvalidTarget {
+                directive: "=debug".to_string()
+            }
+        );
+        assert_eq!(
+            error("a=b=c"),
+            FilterError::Malformed {
+                directive: "a=b=c".to_string()
+            }
+        );
+        assert_eq!(
+            error("x="),
+            FilterError::InvalidLevel {
+                directive: "x=".to_string()
+            }
+        );
+        assert_eq!(
+            error("my crate"),
+            FilterError::InvalidTarget {
+                directive: "my crate".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn bare_target_enables_every_level() {
+        let filter = parsed("warn,my_crate").unwrap();
+        assert_eq!(filter.level_for("my_crate::db"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("other"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn last_bare_level_wins() {
+        let filter = parsed("debug, a=error ,warn").unwrap();
+        assert_eq!(filter.level_for("other"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("a"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn failed_parse_applies_nothing() {
+        let mut filter = Filter::new(LevelFilter::Info);
+        filter.add_directive("a".to_string(), LevelFilter::Warn);
+        assert!(filter.parse("trace,a=debug,b=off,c=loud").is_err());
+        assert_eq!(filter.level_for("other"), LevelFilter::Info);
+        assert_eq!(filter.level_for("a"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("b"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Info);
+    }
+
+    #[test]
+    fn target_matches_whole_path_segments() {
+        let filter = parsed("hyper=off").unwrap();
+        let metadata = |target| {
+            Metadata::builder()
+                .level(Level::Error)
+                .target(target)
+                .build()
+        };
+        assert!(!filter.enabled(&metadata("hyper")));
+        assert!(!filter.enabled(&metadata("hyper::proto::h1")));
+        assert!(filter.enabled(&metadata("hyperlocal")));
+    }
+
+    #[test]
+    fn longest_target_wins() {
+        let filter = parsed("hyper=off,hyper::proto=debug").unwrap();
+        assert_eq!(filter.level_for("hyper::client"), LevelFilter::Off);
+        assert_eq!(filter.level_for("hyper::proto::h1"), LevelFilter::Debug);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+    }
+}
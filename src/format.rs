@@ -0,0 +1,587 @@
+//! Layout of the lines written to the sink.
+
+use crate::color::{Color, DIM, RESET, level_style};
+use crate::timestamp::{Clock, Now, TimeCache, TimeFormat};
+use log::Record;
+use log::kv::{self, Key, Source, Value, VisitSource};
+use std::cell::RefCell;
+use std::fmt::{self, Display, Write};
+use std::ops::Deref;
+use std::str;
+use std::sync::Arc;
+use std::thread::{Thread, ThreadId};
+use std::time::Duration;
+
+/// Layout of each log line.
+#[derive(Copy, Clone, Debug, Default)]
+pub enum OutputFormat {
+    /// Human readable text, `[<time>] (<thread-name> <thread-id>) [<level>] <message>`,
+    /// followed by a ` key=value` pair for each key-value attached to the record.
+    /// Values containing whitespace, `=` or `"` are quoted. See [`TextFormatter`]
+    /// to also write the target, module path or source location.
+    #[default]
+    Text,
+    /// One JSON object per line ([JSON Lines](https://jsonlines.org/)) with the
+    /// fields `time`, `level`, `target`, `thread`, `thread_id`, `module_path`,
+    /// `file`, `line`, `message` and `fields`. Fields that are not known are
+    /// `null`. `fields` is an object holding the key-values attached to the record.
+    /// The source of the record is always included. See [`JsonFormatter`].
+    Json,
+}
+
+/// Writes log records as lines.
+///
+/// Implemented for closures with the same signature as [`Formatter::format`].
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::new_log;
+/// use std::fmt::Write;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .formatter(|buf: &mut String, record: &log::Record, context: &logout::Context| {
+///           write!(buf, "{} {} {}: {}", context.time(), record.level(), record.target(), record.args())
+///       })
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+pub trait Formatter: Send + Sync {
+    /// Append the line for `record` to `buf`, without a trailing newline.
+    ///
+    /// `buf` is empty when called. Should an error be returned, whatever was
+    /// written to `buf` is still logged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if formatting one of the record's values fails.
+    fn format(&self, buf: &mut String, record: &Record, context: &Context) -> fmt::Result;
+}
+
+impl<F> Formatter for F
+where
+    F: Fn(&mut String, &Record, &Context) -> fmt::Result + Send + Sync,
+{
+    fn format(&self, buf: &mut String, record: &Record, context: &Context) -> fmt::Result {
+        self(buf, record, context)
+    }
+}
+
+/// What the logger knows about a record besides the record itself.
+#[derive(Debug)]
+pub struct Context<'a> {
+    pub(crate) time: &'a str,
+    pub(crate) elapsed: Duration,
+    pub(crate) thread: &'a Thread,
+    pub(crate) colored: bool,
+}
+
+impl Context<'_> {
+    /// Time the record was logged, in the configured time format and read
+    /// from the configured [`Clock`](crate::Clock).
+    #[must_use]
+    pub fn time(&self) -> &str {
+        self.time
+    }
+
+    /// Time elapsed between creating the logger and logging the record,
+    /// whichever clock is configured.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Name of the thread that logged the record.
+    #[must_use]
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread.name()
+    }
+
+    /// ID of the thread that logged the record.
+    #[must_use]
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread.id()
+    }
+
+    /// Whether the line may contain ANSI colors, see [`ColorMode`](crate::ColorMode).
+    #[must_use]
+    pub fn colored(&self) -> bool {
+        self.colored
+    }
+}
+
+/// The [`OutputFormat::Text`] layout. When [colored](Context::colored) the
+/// level is colored and the time dimmed.
+///
+/// The source of the record can be added between the level and the message:
+/// its target, its module path and its `file:line`, followed by a colon. Parts
+/// that are not known are left out.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::{new_log, TextFormatter};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     // [<time>] (<thread-name> <thread-id>) [<level>] <target> <file>:<line>: <message>
+///     new_log()
+///       .formatter(TextFormatter::new().target(true).location(true))
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TextFormatter {
+    target: bool,
+    module_path: bool,
+    location: bool,
+}
+
+impl TextFormatter {
+    /// The default layout, without the source of the record.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to write the target of the record.
+    #[must_use]
+    pub fn target(self, target: bool) -> Self {
+        Self { target, ..self }
+    }
+
+    /// Whether to write the module path of the record.
+    #[must_use]
+    pub fn module_path(self, module_path: bool) -> Self {
+        Self {
+            module_path,
+            ..self
+        }
+    }
+
+    /// Whether to write the source file and line of the record.
+    #[must_use]
+    pub fn location(self, location: bool) -> Self {
+        Self { location, ..self }
+    }
+
+    fn write_source(self, buf: &mut String, record: &Record) -> fmt::Result {
+        let start = buf.len();
+        if self.target {
+            write!(buf, " {}", record.target())?;
+        }
+        if self.module_path
+            && let Some(module_path) = record.module_path()
+        {
+            write!(buf, " {module_path}")?;
+        }
+        if self.location
+            && let Some(file) = record.file()
+        {
+            write!(buf, " {file}")?;
+            if let Some(line) = record.line() {
+                write!(buf, ":{line}")?;
+            }
+        }
+        if buf.len() > start {
+            buf.push(':');
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for TextFormatter {
+    fn format(&self, buf: &mut String, record: &Record, context: &Context) -> fmt::Result {
+        let (dim, level, reset) = if context.colored() {
+            (DIM, level_style(record.level()), RESET)
+        } else {
+            ("", "", "")
+        };
+        write!(
+            buf,
+            "[{dim}{}{reset}] ({} {:?}) [{level}{}{reset}]",
+            context.time(),
+            context.thread_name().unwrap_or("<unnamed>"),
+            context.thread_id(),
+            record.level(),
+        )?;
+        self.write_source(buf, record)?;
+        write!(buf, " {}", record.args())?;
+        if record.key_values().count() > 0 {
+            write!(buf, " {}", TextKvs(record.key_values()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The [`OutputFormat::Json`] layout. Never colored.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn format(&self, buf: &mut String, record: &Record, context: &Context) -> fmt::Result {
+        write!(
+            buf,
+            "{{\"time\":{},\"level\":{},\"target\":{},\"thread\":{},\"thread_id\":{},\"module_path\":{},\"file\":{},\"line\":{},\"message\":{},\"fields\":{}}}",
+            JsonStr(context.time()),
+            JsonStr(record.level()),
+            JsonStr(record.target()),
+            JsonOpt(context.thread_name().map(JsonStr)),
+            JsonStr(format_args!("{:?}", context.thread_id())),
+            JsonOpt(record.module_path().map(JsonStr)),
+            JsonOpt(record.file().map(JsonStr)),
+            JsonOpt(record.line()),
+            JsonStr(record.args()),
+            JsonKvs(record.key_values()),
+        )
+    }
+}
+
+/// How the lines of one sink are laid out.
+#[derive(Clone, Debug)]
+pub(crate) struct Layout {
+    pub(crate) time_format: TimeFormat,
+    pub(crate) clock: Clock,
+    pub(crate) formatter: SharedFormatter,
+    pub(crate) color: Color,
+}
+
+impl Layout {
+    /// Default layout for `sink`.
+    pub(crate) fn new<W: 'static>(sink: &W) -> Self {
+        Self {
+            time_format: TimeFormat::Rfc2822,
+            clock: Clock::WallClock,
+            formatter: OutputFormat::Text.into(),
+            color: Color::new(sink),
+        }
+    }
+}
+
+thread_local! {
+    static LINE_BUFFER: RefCell<LineBuffer> = RefCell::default();
+}
+
+/// Buffers a line is formatted into. Each thread keeps one and reuses it from
+/// one message to the next, so that formatting does not allocate once the
+/// buffers have grown to fit the longest line.
+#[derive(Debug, Default)]
+pub(crate) struct LineBuffer {
+    time: Vec<u8>,
+    time_cache: TimeCache,
+    line: String,
+}
+
+impl LineBuffer {
+    /// Run `f` with the calling thread's buffer, or with a new one if that is
+    /// already in use by a formatter that logs, or destroyed along with the
+    /// thread.
+    pub(crate) fn with(f: impl FnOnce(&mut Self)) {
+        let mut f = Some(f);
+        let _ = LINE_BUFFER.try_with(|buffer| {
+            if let Ok(mut buffer) = buffer.try_borrow_mut()
+                && let Some(f) = f.take()
+            {
+                f(&mut buffer);
+            }
+        });
+        if let Some(f) = f {
+            f(&mut Self::default());
+        }
+    }
+
+    /// Format `record` as laid out by `layout`, including the trailing newline.
+    pub(crate) fn format(
+        &mut self,
+        layout: &Layout,
+        record: &Record,
+        now: Now,
+        thread: &Thread,
+    ) -> &str {
+        self.time.clear();
+        let formatted = now.format_into(
+            &mut self.time,
+            &mut self.time_cache,
+            &layout.time_format,
+            layout.clock,
+        );
+        let time = match formatted {
+            Ok(()) => str::from_utf8(&self.time).unwrap_or("time error"),
+            Err(_) => "time error",
+        };
+        let context = Context {
+            time,
+            elapsed: now.elapsed,
+            thread,
+            colored: layout.color.enabled(),
+        };
+        self.line.clear();
+        // Whatever was written before a formatting error is still worth logging.
+        let _ = layout.formatter.format(&mut self.line, record, &context);
+        self.line.push('\n');
+        &self.line
+    }
+}
+
+/// A shared formatter, so loggers and outputs stay cheap to clone and `Debug`.
+#[derive(Clone)]
+pub(crate) struct SharedFormatter(Arc<dyn Formatter>);
+
+impl SharedFormatter {
+    pub(crate) fn new(formatter: impl Formatter + 'static) -> Self {
+        Self(Arc::new(formatter))
+    }
+}
+
+impl From<OutputFormat> for SharedFormatter {
+    fn from(output_format: OutputFormat) -> Self {
+        match output_format {
+            OutputFormat::Text => Self::new(TextFormatter::new()),
+            OutputFormat::Json => Self::new(JsonFormatter),
+        }
+    }
+}
+
+impl Deref for SharedFormatter {
+    type Target = dyn Formatter;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for SharedFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Formatter")
+    }
+}
+
+/// Writes key-values as space separated `key=value` pairs.
+pub(crate) struct TextKvs<'a>(pub(crate) &'a dyn Source);
+
+impl Display for TextKvs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Visitor<'a, 'b> {
+            f: &'a mut fmt::Formatter<'b>,
+            first: bool,
+        }
+
+        impl<'kvs> VisitSource<'kvs> for Visitor<'_, '_> {
+            fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+                if !self.first {
+                    self.f.write_char(' ')?;
+                }
+                self.first = false;
+                // Written twice rather than into a string, to not allocate.
+                let mut check = QuoteCheck {
+                    empty: true,
+                    quote: false,
+                };
+                write!(check, "{value}")?;
+                if check.empty || check.quote {
+                    write!(self.f, "{key}=\"")?;
+                    write!(DebugEscaper(self.f), "{value}")?;
+                    self.f.write_char('"')?;
+                } else {
+                    write!(self.f, "{key}={value}")?;
+                }
+                Ok(())
+            }
+        }
+
+        self.0
+            .visit(&mut Visitor { f, first: true })
+            .map_err(|_| fmt::Error)
+    }
+}
+
+/// Finds out whether a text value needs quoting: when it is empty or contains
+/// whitespace, `=` or `"`.
+struct QuoteCheck {
+    empty: bool,
+    quote: bool,
+}
+
+impl Write for QuoteCheck {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.empty &= s.is_empty();
+        self.quote |= s.contains(|c: char| c.is_whitespace() || c == '=' || c == '"');
+        Ok(())
+    }
+}
+
+/// Escapes everything written through it like the `Debug` output of a string.
+struct DebugEscaper<'a, W: Write>(&'a mut W);
+
+impl<W: Write> Write for DebugEscaper<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c == '\'' {
+                self.0.write_char(c)?;
+            } else {
+                write!(self.0, "{}", c.escape_debug())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes key-values as a JSON object. Booleans and numbers keep their type,
+/// everything else is written as a string.
+struct JsonKvs<'a>(&'a dyn Source);
+
+impl Display for JsonKvs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Visitor<'a, 'b> {
+            f: &'a mut fmt::Formatter<'b>,
+            first: bool,
+        }
+
+        impl<'kvs> VisitSource<'kvs> for Visitor<'_, '_> {
+            fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+                if !self.first {
+                    self.f.write_char(',')?;
+                }
+                self.first = false;
+                write!(self.f, "{}:", JsonStr(key))?;
+                if let Some(v) = value.to_bool() {
+                    write!(self.f, "{v}")?;
+                } else if let Some(v) = value.to_i64() {
+                    write!(self.f, "{v}")?;
+                } else if let Some(v) = value.to_u64() {
+                    write!(self.f, "{v}")?;
+                } else if let Some(v) = value.to_f64().filter(|v| v.is_finite()) {
+                    write!(self.f, "{v}")?;
+                } else {
+                    write!(self.f, "{}", JsonStr(value))?;
+                }
+                Ok(())
+            }
+        }
+
+        f.write_char('{')?;
+        self.0
+            .visit(&mut Visitor { f, first: true })
+            .map_err(|_| fmt::Error)?;
+        f.write_char('}')
+    }
+}
+
+/// Writes the wrapped value as a quoted and escaped JSON string.
+struct JsonStr<T>(T);
+
+impl<T: Display> Display for JsonStr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        write!(JsonEscaper(f), "{}", self.0)?;
+        f.write_char('"')
+    }
+}
+
+/// Writes the wrapped value, or `null` if there is none.
+struct JsonOpt<T>(Option<T>);
+
+impl<T: Display> Display for JsonOpt<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+/// Escapes everything written through it for use inside a JSON string.
+struct JsonEscaper<'a, W: Write>(&'a mut W);
+
+impl<W: Write> Write for JsonEscaper<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            if !matches!(c, '"' | '\\' | '\u{0}'..='\u{1f}') {
+                continue;
+            }
+            self.0.write_str(&s[start..i])?;
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c => write!(self.0, "\\u{:04x}", u32::from(c))?,
+            }
+            start = i + c.len_utf8();
+        }
+        self.0.write_str(&s[start..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timezone;
+    use std::thread;
+    use std::time::Instant;
+
+    fn json_line(args: fmt::Arguments, kvs: &dyn Source) -> String {
+        let layout = Layout {
+            time_format: TimeFormat::UnixSeconds,
+            formatter: OutputFormat::Json.into(),
+            ..Layout::new(&Vec::<u8>::new())
+        };
+        let record = Record::builder().args(args).key_values(&kvs).build();
+        let now = Now::new(Timezone::Utc, Instant::now());
+        LineBuffer::default()
+            .format(&layout, &record, now, &thread::current())
+            .to_string()
+    }
+
+    #[test]
+    fn json_escapes_strings() {
+        assert_eq!(
+            JsonStr("line 1\nline\t2 \"quoted\" C:\\dir \u{1}\r").to_string(),
+            r#""line 1\nline\t2 \"quoted\" C:\\dir \u0001\r""#
+        );
+        assert_eq!(
+            JsonStr("grüße, 日本語 🦀").to_string(),
+            "\"grüße, 日本語 🦀\""
+        );
+        assert_eq!(JsonStr("\u{1f}\u{7f}").to_string(), "\"\\u001f\u{7f}\"");
+    }
+
+    #[test]
+    fn json_keeps_field_types() {
+        let kvs = [
+            ("bool", Value::from(true)),
+            ("int", Value::from(-3_i64)),
+            ("uint", Value::from(u64::MAX)),
+            ("float", Value::from(1.5_f64)),
+            ("nan", Value::from(f64::NAN)),
+            ("inf", Value::from(f64::INFINITY)),
+            ("str", Value::from("42")),
+        ];
+        assert_eq!(
+            JsonKvs(&kvs).to_string(),
+            format!(
+                r#"{{"bool":true,"int":-3,"uint":{},"float":1.5,"nan":"NaN","inf":"inf","str":"42"}}"#,
+                u64::MAX
+            )
+        );
+        let none: [(&str, Value); 0] = [];
+        assert_eq!(JsonKvs(&none).to_string(), "{}");
+    }
+
+    #[test]
+    fn json_line_is_a_single_line() {
+        let kvs = [("key \"k\"", Value::from("a\nb"))];
+        let line = json_line(format_args!("multi\nline ü"), &kvs);
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        assert!(line.contains(r#","message":"multi\nline ü","#), "{line}");
+        assert!(
+            line.ends_with(concat!(r#","fields":{"key \"k\"":"a\nb"}}"#, "\n")),
+            "{line}"
+        );
+    }
+}
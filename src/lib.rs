@@ -1,2 +1,28 @@
+mod asynchronous;
+mod color;
+mod compress;
+#[cfg(feature = "config")]
+mod config;
+mod filter;
+mod format;
 mod logout;
-pub use logout::{TimeFormat, new_log};
+mod output;
+mod pattern;
+mod reopen;
+mod rotate;
+mod sink;
+mod timestamp;
+pub use asynchronous::{DroppedMessages, OverflowPolicy};
+pub use color::ColorMode;
+pub use compress::Compression;
+#[cfg(feature = "config")]
+pub use config::{ConfigError, ConfigWatcher, LogConfig};
+pub use filter::FilterError;
+pub use format::{Context, Formatter, JsonFormatter, OutputFormat, TextFormatter};
+pub use logout::{LogGuard, LogHandle, new_log};
+pub use output::Output;
+pub use pattern::{Pattern, PatternError};
+pub use reopen::{ReopenHandle, ReopenableFile};
+pub use rotate::{Period, RotatingFile, TimedFile};
+pub use sink::{AtomicWrite, FailedWrites, WriteErrorPolicy};
+pub use timestamp::{Clock, CustomTimeFormat, TimeFormat, TimeFormatError, Timezone};
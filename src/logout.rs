@@ -11,30 +11,67 @@
 //! ```
 //!
 //! Where:
-//! `<time>` is the current time with utc-offset (if available). Available format RFC2822 and RFC3339.
+//! `<time>` is the current time in the configured [`Timezone`], see [`TimeFormat`] for the available formats.
+//! With [`Clock::Elapsed`] it is the time since the logger was created instead.
 //! `<thread-name>` and `<thread-id>` are thread identifiers defined by `std::thread`.
 //! `<level>` is the log level as defined by `log::LogLevel`.
-//! `<message>` is the log message.
+//! `<message>` is the log message, followed by any key-values attached to the
+//! record as ` key=value` pairs.
+//!
+//! [`TextFormatter`](crate::TextFormatter) can also write the target, module
+//! path and `file:line` of each message.
+//!
+//! Alternatively [`OutputFormat::Json`] writes each message as a single JSON
+//! object per line.
 //!
 //! # Errors
 //!
-//! Best effort is made to handle errors. Write failures result in falling back to `stderr`.
+//! Best effort is made to handle errors. Write failures result in falling back to `stderr`,
+//! unless another [`WriteErrorPolicy`] is set with `Logger::on_write_error`.
+//! Should a thread panic while writing to a sink, for instance in a custom
+//! `Write` implementation, the sink keeps being written to afterwards.
+//!
+//! # Shutdown
+//!
+//! The logger lives until the end of the process and is never dropped, so a
+//! buffered sink or the queue of an asynchronous logger may still hold the last
+//! messages when `main` returns. Keep the [`LogGuard`] returned by
+//! `Logger::enable_with_guard` alive until then to have them written out.
 //!
 //! # Performance
 //!
 //! The logger relies on a global `Mutex` to serialize access to the user
-//! supplied sink.
+//! supplied sink. In [asynchronous](Logger::asynchronous) mode the calling
+//! thread only formats the message and queues it, a background thread does the
+//! writing. Sinks that write whole lines atomically, such as files opened in
+//! append mode, can be written to without the `Mutex` with
+//! [`Logger::atomic_sink`].
+//!
+//! Lines are formatted into a buffer kept by each thread and written from it
+//! in one go, so a synchronous logger does not allocate once the buffer has
+//! grown to fit. An asynchronous logger allocates each queued line.
+//! Each thread also keeps the time formatted at the start of the current
+//! second, so that only the sub-second digits are rendered for each message.
 
+use crate::asynchronous::{DroppedMessages, Line, OverflowPolicy, Queue, Writer};
+use crate::color::{Color, ColorMode};
+use crate::filter::{Filter, FilterError, SharedFilter};
+use crate::format::{Formatter, Layout, LineBuffer, OutputFormat, SharedFormatter};
+use crate::output::Output;
+use crate::pattern::{Pattern, PatternError};
+use crate::rotate::{Period, RotatingFile, TimedFile};
+use crate::sink::{AtomicWrite, FailedWrites, Sink, WriteErrorPolicy, WriteErrors};
+use crate::timestamp::{Clock, Now, TimeFormat, Timezone};
 use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::env::{self, VarError};
+use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{Stderr, Write};
+use std::marker::PhantomData;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::Arc;
 use std::thread;
-use time::{
-    OffsetDateTime,
-    format_description::well_known::{Rfc2822, Rfc3339},
-};
+use std::time::Instant;
 
 /// Configure the [`log`](https://crates.io/crates/log) facade.
 ///
@@ -44,8 +81,9 @@ use time::{
 /// ```rust
 /// use logout::new_log;
 ///
-/// # fn main() -> Result<()> {
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     new_log().enable()?;
+/// #   Ok(())
 /// # }
 /// ```
 ///
@@ -53,14 +91,15 @@ use time::{
 /// ```rust
 /// use log::LevelFilter;
 /// use logout::{new_log, TimeFormat};
-/// use std::io;
 ///
-/// # fn main() -> Result<()> {
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let log_path = std::env::temp_dir().join("logout-doctest.log");
 ///     new_log()
 ///       .to_file(&log_path)?
 ///       .max_log_level(LevelFilter::Info)
 ///       .time_format(TimeFormat::Rfc2822)
 ///       .enable()?;
+/// #   Ok(())
 /// # }
 /// ```
 ///
@@ -68,13 +107,83 @@ use time::{
 /// ```rust
 /// use log::LevelFilter;
 /// use logout::new_log;
-/// use std::io;
 ///
-/// # fn main() -> Result<()> {
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     new_log()
-///       .sink(std::io:stderr())?
+///       .sink(std::io::stdout())
 ///       .max_log_level(LevelFilter::Info)
 ///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// Keep our own crates at `Debug` while silencing a chatty dependency.
+/// ```rust
+/// use log::LevelFilter;
+/// use logout::new_log;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .max_log_level(LevelFilter::Warn)
+///       .target_log_level("my_crate", LevelFilter::Debug)
+///       .target_log_level("hyper", LevelFilter::Off)
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// Write JSON Lines to a file.
+/// ```rust
+/// use logout::{new_log, OutputFormat, TimeFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let log_path = std::env::temp_dir().join("logout-doctest.jsonl");
+///     new_log()
+///       .to_file(&log_path)?
+///       .output_format(OutputFormat::Json)
+///       .time_format(TimeFormat::Rfc3339)
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// Write from a background thread, dropping the oldest messages rather than
+/// blocking when the writer cannot keep up.
+/// ```rust
+/// use logout::{new_log, OverflowPolicy};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let logger = new_log().asynchronous(4096, OverflowPolicy::DropOldest);
+///     let dropped = logger.dropped_messages();
+///     // Everything queued is written out when `_guard` goes out of scope.
+///     let _guard = logger.enable_with_guard()?;
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// Timestamp in UTC with millisecond precision.
+/// ```rust
+/// use logout::{new_log, TimeFormat, Timezone};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .timezone(Timezone::Utc)
+///       .time_format(TimeFormat::Iso8601Millis)
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+///
+/// Read the level configuration from the `RUST_LOG` environment variable.
+/// ```rust
+/// use logout::new_log;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .parse_filters("warn,my_crate=debug")?
+///       .from_env("RUST_LOG")?
+///       .enable()?;
+/// #   Ok(())
 /// # }
 /// ```
 #[must_use]
@@ -82,106 +191,471 @@ pub fn new_log() -> Logger<Stderr> {
     Logger::new(std::io::stderr())
 }
 
-#[derive(Copy, Clone, Debug)]
-pub enum TimeFormat {
-    Rfc2822,
-    Rfc3339,
-}
-
 #[derive(Debug)]
 pub struct Logger<T: Write + Send + 'static> {
-    sink: Mutex<T>,
-    time_format: TimeFormat,
-    level: LevelFilter,
+    sink: Sink,
+    sink_type: PhantomData<fn() -> T>,
+    outputs: Vec<Output>,
+    queue: Option<Arc<Queue>>,
+    start: Instant,
+    timezone: Timezone,
+    layout: Layout,
+    filter: SharedFilter,
+    errors: Arc<WriteErrors>,
 }
 
 impl<T: Write + Send + 'static> Logger<T> {
     fn new(sink: T) -> Self {
         Self {
-            sink: Mutex::new(sink),
-            time_format: TimeFormat::Rfc2822,
-            level: LevelFilter::Info,
+            layout: Layout::new(&sink),
+            sink: Sink::locked(sink),
+            sink_type: PhantomData,
+            outputs: Vec::new(),
+            queue: None,
+            start: Instant::now(),
+            timezone: Timezone::Local,
+            filter: SharedFilter::new(Filter::new(LevelFilter::Info)),
+            errors: Arc::new(WriteErrors::new(WriteErrorPolicy::default())),
         }
     }
 
     pub fn to_file(&self, path: impl AsRef<Path>) -> Result<Logger<File>, std::io::Error> {
         let sink = OpenOptions::new().append(true).create(true).open(path)?;
-        Ok(Logger {
-            sink: Mutex::new(sink),
-            time_format: self.time_format,
-            level: self.level,
-        })
+        Ok(self.sink(sink))
+    }
+
+    /// Log to `path`, rolling it over to `path.1`, `path.2`, ... once it grows
+    /// beyond `max_size` bytes. See [`RotatingFile`].
+    pub fn to_rotating_file(
+        &self,
+        path: impl AsRef<Path>,
+        max_size: u64,
+        max_archives: usize,
+    ) -> Result<Logger<RotatingFile>, std::io::Error> {
+        Ok(self.sink(RotatingFile::open(path, max_size, max_archives)?))
+    }
+
+    /// Log to `path` suffixed with a timestamp, starting a new file every
+    /// `period`. See [`TimedFile`].
+    ///
+    /// Periods start in the [time zone](Logger::timezone) of the logger, which
+    /// must therefore be set before calling this.
+    pub fn to_timed_file(
+        &self,
+        path: impl AsRef<Path>,
+        period: Period,
+        max_age_days: Option<u32>,
+    ) -> Result<Logger<TimedFile>, std::io::Error> {
+        let file = TimedFile::open_with_timezone(path, period, max_age_days, self.timezone)?;
+        Ok(self.sink(file))
     }
 
     pub fn sink<U: Write + Send + 'static>(&self, sink: U) -> Logger<U> {
+        let color = self.layout.color.sink(&sink);
+        self.with_sink(color, Sink::locked(sink))
+    }
+
+    /// Like [`Logger::sink`], for a sink that writes each line with a single
+    /// system call, such as a file opened in append mode. The sink is written to
+    /// without a lock, so threads logging at the same time do not wait for each
+    /// other. See [`AtomicWrite`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use logout::new_log;
+    /// use std::fs::OpenOptions;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// #   let log_path = std::env::temp_dir().join("logout-doctest-atomic.log");
+    ///     let file = OpenOptions::new().append(true).create(true).open(&log_path)?;
+    ///     new_log()
+    ///       .atomic_sink(file)
+    ///       .enable()?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn atomic_sink<U: AtomicWrite + Write + Send + 'static>(&self, sink: U) -> Logger<U> {
+        let color = self.layout.color.sink(&sink);
+        self.with_sink(color, Sink::atomic(sink))
+    }
+
+    fn with_sink<U: Write + Send + 'static>(&self, color: Color, sink: Sink) -> Logger<U> {
         Logger {
-            sink: Mutex::new(sink),
-            time_format: self.time_format,
-            level: self.level,
+            layout: Layout {
+                color,
+                ..self.layout.clone()
+            },
+            sink,
+            sink_type: PhantomData,
+            outputs: self.outputs.clone(),
+            queue: self.queue.clone(),
+            start: self.start,
+            timezone: self.timezone,
+            filter: self.filter.clone(),
+            errors: Arc::clone(&self.errors),
         }
     }
 
-    pub fn time_format(self, time_format: TimeFormat) -> Self {
-        Self {
-            sink: self.sink,
-            time_format,
-            level: self.level,
-        }
+    pub fn time_format(mut self, time_format: TimeFormat) -> Self {
+        self.layout.time_format = time_format;
+        self
+    }
+
+    /// Time zone of the timestamps, shared by all outputs.
+    pub fn timezone(self, timezone: Timezone) -> Self {
+        Self { timezone, ..self }
+    }
+
+    /// Whether lines show the wall-clock time, the time elapsed since the
+    /// logger was created, or both.
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.layout.clock = clock;
+        self
+    }
+
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.layout.formatter = output_format.into();
+        self
+    }
+
+    /// Whether to color the level and dim the time. By default lines are only
+    /// colored when writing to a terminal, never when writing to a file.
+    pub fn color(mut self, mode: ColorMode) -> Self {
+        self.layout.color = self.layout.color.mode(mode);
+        self
+    }
+
+    /// Lay out lines with a custom [`Formatter`] instead of an [`OutputFormat`].
+    pub fn formatter(mut self, formatter: impl Formatter + 'static) -> Self {
+        self.layout.formatter = SharedFormatter::new(formatter);
+        self
     }
 
     pub fn max_log_level(self, level: LevelFilter) -> Self {
+        self.filter.write().set_level(level);
+        self
+    }
+
+    /// Override the level for `target` and every module below it. When several
+    /// overrides match a record's target, the longest one wins.
+    pub fn target_log_level(self, target: impl Into<String>, level: LevelFilter) -> Self {
+        self.filter.write().add_directive(target.into(), level);
+        self
+    }
+
+    /// Replace the levels of the logger's own sink.
+    #[cfg(feature = "config")]
+    pub(crate) fn filter(self, filter: Filter) -> Self {
+        *self.filter.write() = filter;
+        self
+    }
+
+    /// Also write to `output`, which has its own levels and formats. Each
+    /// call adds another destination.
+    pub fn output(mut self, output: Output) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    /// Lay out lines according to a [`Pattern`] template such as
+    /// `"{time} {level:>5} {target}: {message}"`.
+    pub fn pattern(self, template: &str) -> Result<Self, PatternError> {
+        Ok(self.formatter(Pattern::parse(template)?))
+    }
+
+    /// Configure levels from a `RUST_LOG` style directive string such as
+    /// `info,my_crate=debug,hyper::proto=off`.
+    pub fn parse_filters(self, spec: &str) -> Result<Self, FilterError> {
+        self.filter.write().parse(spec)?;
+        Ok(self)
+    }
+
+    /// Configure levels from the directive string in the environment variable
+    /// `var_name`. The configuration is left unchanged if the variable is not set.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_env(self, var_name: &str) -> Result<Self, FilterError> {
+        match env::var(var_name) {
+            Ok(spec) => self.parse_filters(&spec),
+            Err(VarError::NotPresent) => Ok(self),
+            Err(VarError::NotUnicode(_)) => Err(FilterError::NotUnicode {
+                var: var_name.to_string(),
+            }),
+        }
+    }
+
+    /// Write from a background thread. Messages are formatted on the calling
+    /// thread and queued, holding at most `capacity` messages; `overflow`
+    /// decides what happens when the queue is full.
+    pub fn asynchronous(self, capacity: usize, overflow: OverflowPolicy) -> Self {
         Self {
-            sink: self.sink,
-            time_format: self.time_format,
-            level,
+            queue: Some(Arc::new(Queue::new(capacity, overflow))),
+            ..self
         }
     }
 
-    pub fn enable(self) -> Result<(), SetLoggerError> {
-        log::set_max_level(self.level);
-        // Will fail if `set_logger` or `set_boxed_logger` has already been called.
-        log::set_boxed_logger(Box::new(self))
+    /// Counter of messages dropped in [asynchronous](Logger::asynchronous)
+    /// mode, `None` if the logger is synchronous.
+    pub fn dropped_messages(&self) -> Option<DroppedMessages> {
+        self.queue.as_ref().map(|queue| queue.dropped())
     }
 
-    fn log(&self, record: &Record) {
-        let now = match OffsetDateTime::now_local() {
-            Ok(now_local) => now_local,
-            Err(_) => OffsetDateTime::now_utc(),
-        };
+    /// What to do when writing a line fails, for the logger's own sink and the
+    /// sinks of its outputs. By default the line is written to stderr instead.
+    pub fn on_write_error(mut self, policy: WriteErrorPolicy) -> Self {
+        self.errors = Arc::new(self.errors.policy(policy));
+        self
+    }
+
+    /// Counter of lines that could not be written to their sink.
+    pub fn failed_writes(&self) -> FailedWrites {
+        self.errors.failed()
+    }
+
+    /// Install the logger. The returned handle changes its levels later on.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use log::LevelFilter;
+    /// use logout::new_log;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let handle = new_log().enable()?;
+    ///     // Later, while investigating a problem:
+    ///     handle.set_target_log_level("my_crate::solver", LevelFilter::Debug);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn enable(self) -> Result<LogHandle, SetLoggerError> {
+        // Without a guard the writer thread, if any, is left running until the
+        // process exits.
+        self.install().map(|(handle, _writer)| handle)
+    }
+
+    /// Like [`Logger::enable`], but returns a guard that flushes the sink when
+    /// dropped. In asynchronous mode the guard also waits for the writer thread
+    /// to write out every queued message; later messages are written
+    /// synchronously.
+    pub fn enable_with_guard(self) -> Result<LogGuard, SetLoggerError> {
+        let sinks = self.sinks();
+        let (handle, writer) = self.install()?;
+        Ok(LogGuard {
+            sinks,
+            writer,
+            handle,
+        })
+    }
+
+    /// A handle changing the filters of this logger itself.
+    pub(crate) fn handle(&self) -> LogHandle {
+        LogHandle {
+            filter: self.filter.share(),
+            outputs: self
+                .outputs
+                .iter()
+                .map(|output| output.filter.share())
+                .collect(),
+        }
+    }
+
+    fn install(self) -> Result<(LogHandle, Option<Writer>), SetLoggerError> {
+        // Determine the local offset now rather than on the first message,
+        // while the program is most likely still single threaded.
+        let _ = self.timezone.offset();
+        let sinks = self.sinks();
+        let queue = self.queue.clone();
+        let errors = Arc::clone(&self.errors);
+        let handle = self.handle();
+        handle.sync_max_level(&self.filter.read());
+        // Will fail if `set_logger` or `set_boxed_logger` has already been called.
+        log::set_boxed_logger(Box::new(self))?;
 
-        let now = match self.time_format {
-            TimeFormat::Rfc2822 => now.format(&Rfc2822),
-            TimeFormat::Rfc3339 => now.format(&Rfc3339),
+        let Some(queue) = queue else {
+            return Ok((handle, None));
         };
+        match Writer::spawn(Arc::clone(&queue), sinks, errors) {
+            Ok(writer) => Ok((handle, Some(writer))),
+            Err(e) => {
+                eprintln!("error starting writer thread, logging synchronously: {e}");
+                queue.close();
+                Ok((handle, None))
+            }
+        }
+    }
 
-        let msg = format!(
-            "[{}] ({} {:?}) [{}] {}",
-            now.unwrap_or("time error".to_string()),
-            thread::current().name().unwrap_or("<unnamed>"),
-            thread::current().id(),
-            record.level(),
-            record.args()
-        );
-
-        match self.sink.lock() {
-            Ok(mut sink) => {
-                if let Err(e) = writeln!(sink, "{msg}") {
-                    // Fallback write to stderr.
-                    eprintln!("error writing to sink, falling back to stderr: {e}");
-                    eprintln!("{msg}");
-                }
+    /// The logger's own sink followed by the sinks of its outputs. A line's
+    /// sink index refers to this order.
+    fn sinks(&self) -> Vec<Sink> {
+        std::iter::once(self.sink.clone())
+            .chain(self.outputs.iter().map(|output| output.writer.clone()))
+            .collect()
+    }
+
+    fn log(&self, record: &Record) {
+        let now = Now::new(self.timezone, self.start);
+        let thread = thread::current();
+        LineBuffer::with(|buffer| {
+            if self.filter.read().enabled(record.metadata()) {
+                self.write(0, buffer.format(&self.layout, record, now, &thread));
             }
-            Err(_) => {
-                // Fallback write to stderr.
-                eprintln!("{msg}");
+            for (i, output) in self.outputs.iter().enumerate() {
+                if output.enabled(record.metadata()) {
+                    self.write(i + 1, buffer.format(&output.layout, record, now, &thread));
+                }
             }
-        };
+        });
+    }
+
+    fn write(&self, sink: usize, text: &str) {
+        // The queue hands the line back once the writer has been stopped.
+        if let Some(queue) = &self.queue
+            && queue
+                .push(Line {
+                    sink,
+                    text: text.to_string(),
+                })
+                .is_ok()
+        {
+            return;
+        }
+        match sink {
+            0 => self.sink.write_line(text, &self.errors),
+            i => self.outputs[i - 1].writer.write_line(text, &self.errors),
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(queue) = &self.queue {
+            queue.wait_idle();
+        }
+        self.sink.flush();
+        for output in &self.outputs {
+            output.writer.flush();
+        }
+    }
+}
+
+/// Flushes the logger, and stops the writer thread of an asynchronous logger,
+/// when dropped. Returned by `Logger::enable_with_guard`.
+#[must_use = "the logger is flushed when the guard is dropped"]
+pub struct LogGuard {
+    sinks: Vec<Sink>,
+    writer: Option<Writer>,
+    handle: LogHandle,
+}
+
+impl LogGuard {
+    /// A handle to change the levels of the logger, as returned by
+    /// `Logger::enable`.
+    #[must_use]
+    pub fn handle(&self) -> LogHandle {
+        self.handle.clone()
+    }
+}
+
+impl fmt::Debug for LogGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogGuard")
+            .field("asynchronous", &self.writer.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            writer.stop();
+        }
+        for sink in &self.sinks {
+            sink.flush();
+        }
+    }
+}
+
+/// Changes the levels of an enabled logger. Returned by `Logger::enable` and
+/// [`LogGuard::handle`], and cheap to clone.
+///
+/// The levels of the logger's own sink are changed; outputs keep the levels
+/// they were given. [`log::max_level`] is kept in sync, so raising a level
+/// takes effect for every target at once.
+#[derive(Debug)]
+pub struct LogHandle {
+    filter: SharedFilter,
+    outputs: Arc<[SharedFilter]>,
+}
+
+impl Clone for LogHandle {
+    /// Another handle to the same logger. Cloning the filter would give the
+    /// clone a copy of its own.
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.share(),
+            outputs: Arc::clone(&self.outputs),
+        }
+    }
+}
+
+impl LogHandle {
+    /// Set the level for targets without an override.
+    pub fn set_max_log_level(&self, level: LevelFilter) {
+        self.update(|filter| filter.set_level(level));
+    }
+
+    /// Override the level for `target` and every module below it, replacing
+    /// any previous override for `target`.
+    pub fn set_target_log_level(&self, target: impl Into<String>, level: LevelFilter) {
+        let target = target.into();
+        self.update(|filter| filter.add_directive(target, level));
+    }
+
+    /// Remove the override for `target`, so that it is logged at the level of
+    /// the closest enclosing override or the default level again.
+    pub fn remove_target_log_level(&self, target: &str) {
+        self.update(|filter| filter.remove_directive(target));
+    }
+
+    /// Apply a `RUST_LOG` style directive string, see `Logger::parse_filters`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, and changes nothing, if a directive cannot be parsed.
+    pub fn parse_filters(&self, spec: &str) -> Result<(), FilterError> {
+        self.update(|filter| filter.parse(spec))
+    }
+
+    fn update<R>(&self, change: impl FnOnce(&mut Filter) -> R) -> R {
+        let mut filter = self.filter.write();
+        let result = change(&mut filter);
+        self.sync_max_level(&filter);
+        result
+    }
+
+    /// Replace the filters of the logger's own sink and of as many of its
+    /// outputs as `outputs` holds filters for.
+    #[cfg(feature = "config")]
+    pub(crate) fn replace_filters(&self, filter: Filter, outputs: Vec<Filter>) {
+        let mut own = self.filter.write();
+        for (shared, filter) in self.outputs.iter().zip(outputs) {
+            *shared.write() = filter;
+        }
+        *own = filter;
+        self.sync_max_level(&own);
+    }
+
+    fn sync_max_level(&self, filter: &Filter) {
+        let max_level = self
+            .outputs
+            .iter()
+            .map(|output| output.read().max_level())
+            .fold(filter.max_level(), Ord::max);
+        log::set_max_level(max_level);
     }
 }
 
 impl<T: Write + Send + 'static> Log for Logger<T> {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        self.filter.read().enabled(metadata) || self.outputs.iter().any(|o| o.enabled(metadata))
     }
 
     fn log(&self, record: &Record) {
@@ -190,5 +664,32 @@ impl<T: Write + Send + 'static> Log for Logger<T> {
         }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn metadata(level: Level, target: &str) -> Metadata<'_> {
+        Metadata::builder().level(level).target(target).build()
+    }
+
+    #[test]
+    fn cloned_handle_changes_the_logger() {
+        let logger = new_log();
+        let handle = logger.handle().clone();
+        assert!(!Log::enabled(&logger, &metadata(Level::Debug, "app")));
+
+        handle.set_max_log_level(LevelFilter::Debug);
+        assert!(Log::enabled(&logger, &metadata(Level::Debug, "app")));
+
+        handle.set_target_log_level("app::db", LevelFilter::Trace);
+        assert!(Log::enabled(&logger, &metadata(Level::Trace, "app::db")));
+        handle.clone().remove_target_log_level("app::db");
+        assert!(!Log::enabled(&logger, &metadata(Level::Trace, "app::db")));
+    }
 }
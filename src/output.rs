@@ -0,0 +1,145 @@
+//! Additional destinations for log messages.
+
+use crate::color::ColorMode;
+use crate::filter::{Filter, SharedFilter};
+use crate::format::{Formatter, Layout, OutputFormat, SharedFormatter};
+use crate::pattern::{Pattern, PatternError};
+use crate::sink::{AtomicWrite, Sink};
+use crate::timestamp::{Clock, TimeFormat};
+use log::{LevelFilter, Metadata};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A destination written to in addition to the logger's own sink, with its own
+/// levels, time format and formatter. Attach it with `Logger::output`.
+///
+/// Each destination is locked and written separately, so a destination that
+/// fails to write does not affect the others.
+///
+/// # Examples
+///
+/// Info and above on stderr, everything into a file.
+/// ```rust
+/// use log::LevelFilter;
+/// use logout::{new_log, Output, OutputFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let log_path = std::env::temp_dir().join("logout-doctest-output.jsonl");
+///     new_log()
+///       .max_log_level(LevelFilter::Info)
+///       .output(
+///           Output::file(&log_path)?
+///             .max_log_level(LevelFilter::Trace)
+///             .output_format(OutputFormat::Json),
+///       )
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Output {
+    pub(crate) writer: Sink,
+    pub(crate) filter: SharedFilter,
+    pub(crate) layout: Layout,
+}
+
+impl Output {
+    /// Write to `writer`, with the same defaults as `new_log`.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            layout: Layout::new(&writer),
+            writer: Sink::locked(writer),
+            filter: SharedFilter::new(Filter::new(LevelFilter::Info)),
+        }
+    }
+
+    /// Write to `writer` without a lock, see `Logger::atomic_sink`.
+    pub fn atomic<W: AtomicWrite + 'static>(writer: W) -> Self {
+        Self {
+            layout: Layout::new(&writer),
+            writer: Sink::atomic(writer),
+            filter: SharedFilter::new(Filter::new(LevelFilter::Info)),
+        }
+    }
+
+    /// Append to the file at `path`, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn file(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(Self::new(file))
+    }
+
+    #[must_use]
+    pub fn time_format(mut self, time_format: TimeFormat) -> Self {
+        self.layout.time_format = time_format;
+        self
+    }
+
+    /// Which clock the time is read from, see `Logger::clock`. Elapsed time is
+    /// measured from the creation of the logger this output is attached to.
+    #[must_use]
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.layout.clock = clock;
+        self
+    }
+
+    #[must_use]
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.layout.formatter = output_format.into();
+        self
+    }
+
+    /// Lay out lines with a custom [`Formatter`] instead of an [`OutputFormat`].
+    #[must_use]
+    pub fn formatter(mut self, formatter: impl Formatter + 'static) -> Self {
+        self.layout.formatter = SharedFormatter::new(formatter);
+        self
+    }
+
+    /// Whether to color the level and dim the time, see `Logger::color`.
+    #[must_use]
+    pub fn color(mut self, mode: ColorMode) -> Self {
+        self.layout.color = self.layout.color.mode(mode);
+        self
+    }
+
+    /// Lay out lines according to a [`Pattern`] template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template is invalid.
+    pub fn pattern(self, template: &str) -> Result<Self, PatternError> {
+        Ok(self.formatter(Pattern::parse(template)?))
+    }
+
+    #[must_use]
+    pub fn max_log_level(self, level: LevelFilter) -> Self {
+        self.filter.write().set_level(level);
+        self
+    }
+
+    /// Override the level for `target` and every module below it.
+    #[must_use]
+    pub fn target_log_level(self, target: impl Into<String>, level: LevelFilter) -> Self {
+        self.filter.write().add_directive(target.into(), level);
+        self
+    }
+
+    pub(crate) fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.read().enabled(metadata)
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output")
+            .field("filter", &self.filter)
+            .field("layout", &self.layout)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,401 @@
+//! Line layouts described by a template string.
+
+use crate::color::{DIM, RESET, level_style};
+use crate::format::{Context, Formatter, TextKvs};
+use crate::timestamp::Elapsed;
+use log::Record;
+use std::error::Error;
+use std::fmt::{self, Display, Write};
+
+/// Error returned when a [`Pattern`] template cannot be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatternError {
+    /// `{name}` is not one of the known placeholders.
+    UnknownPlaceholder { name: String },
+    /// The width or alignment after the `:` in `{placeholder}` is invalid.
+    InvalidSpec { placeholder: String },
+    /// A `{` is not closed, or a `}` is not opened. Literal braces are written
+    /// `{{` and `}}`.
+    UnmatchedBrace,
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPlaceholder { name } => write!(f, "unknown placeholder `{{{name}}}`"),
+            Self::InvalidSpec { placeholder } => {
+                write!(f, "invalid width or alignment in `{{{placeholder}}}`")
+            }
+            Self::UnmatchedBrace => {
+                f.write_str("unmatched brace, write `{{` or `}}` for a literal brace")
+            }
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+/// A [`Formatter`] laying out lines according to a template such as
+/// `"{time} {level:>5} {target}: {message}"`, parsed once up front.
+///
+/// The placeholders are:
+///
+/// | Placeholder     | Value                                                  |
+/// |-----------------|--------------------------------------------------------|
+/// | `{time}`        | time in the configured time format and clock           |
+/// | `{elapsed}`     | time since the logger was created, `+12.345678s`       |
+/// | `{level}`       | log level                                              |
+/// | `{thread}`      | thread name, `<unnamed>` if it has none                |
+/// | `{thread_id}`   | thread ID                                              |
+/// | `{target}`      | log target                                             |
+/// | `{module_path}` | module path, empty if unknown                          |
+/// | `{file}`        | source file, empty if unknown                          |
+/// | `{line}`        | source line, empty if unknown                          |
+/// | `{message}`     | log message                                            |
+/// | `{kv}`          | key-values as space separated `key=value` pairs        |
+///
+/// A placeholder can be given a minimum width and alignment as in
+/// [`std::fmt`]: `{level:>5}`, `{target:<20}`, `{thread:*^10}`. Values are
+/// left aligned by default.
+///
+/// When [colored](Context::colored), `{level}` is colored and `{time}` and
+/// `{elapsed}` dimmed.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::new_log;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .pattern("{time} {level:>5} {target}: {message} {kv}")?
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Literal(String),
+    Field { field: Field, spec: Spec },
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Field {
+    Time,
+    Elapsed,
+    Level,
+    Thread,
+    ThreadId,
+    Target,
+    ModulePath,
+    File,
+    Line,
+    Message,
+    Kv,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "time" => Self::Time,
+            "elapsed" => Self::Elapsed,
+            "level" => Self::Level,
+            "thread" => Self::Thread,
+            "thread_id" => Self::ThreadId,
+            "target" => Self::Target,
+            "module_path" => Self::ModulePath,
+            "file" => Self::File,
+            "line" => Self::Line,
+            "message" => Self::Message,
+            "kv" => Self::Kv,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Self::Left),
+            '>' => Some(Self::Right),
+            '^' => Some(Self::Center),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Spec {
+    fill: char,
+    align: Align,
+    width: usize,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Self> {
+        let mut chars = spec.chars();
+        let (fill, align, width) = match (chars.next(), chars.next()) {
+            (Some(fill), Some(c)) if Align::from_char(c).is_some() => {
+                (fill, Align::from_char(c)?, chars.as_str())
+            }
+            (Some(c), _) if Align::from_char(c).is_some() => {
+                (' ', Align::from_char(c)?, &spec[c.len_utf8()..])
+            }
+            _ => (' ', Align::Left, spec),
+        };
+        let width = if width.is_empty() {
+            0
+        } else if width.bytes().all(|b| b.is_ascii_digit()) {
+            width.parse().ok()?
+        } else {
+            return None;
+        };
+        Some(Self { fill, align, width })
+    }
+
+    /// Write `value`, padded to the width.
+    fn write(self, buf: &mut String, value: impl Display) -> fmt::Result {
+        let start = buf.len();
+        write!(buf, "{value}")?;
+        let padding = self.width.saturating_sub(buf[start..].chars().count());
+        if padding == 0 {
+            return Ok(());
+        }
+        let (before, after) = match self.align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        // Inserted one at a time rather than collected, to not allocate.
+        for _ in 0..before {
+            buf.insert(start, self.fill);
+        }
+        buf.extend(std::iter::repeat_n(self.fill, after));
+        Ok(())
+    }
+}
+
+impl Pattern {
+    /// Parse `template`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a placeholder is unknown or has an invalid width or
+    /// alignment, or if a brace is unmatched.
+    pub fn parse(template: &str) -> Result<Self, PatternError> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..i]);
+            let brace = &rest[i..];
+            if brace.starts_with("{{") || brace.starts_with("}}") {
+                literal.push_str(&brace[..1]);
+                rest = &brace[2..];
+                continue;
+            }
+            if brace.starts_with('}') {
+                return Err(PatternError::UnmatchedBrace);
+            }
+            let end = brace.find('}').ok_or(PatternError::UnmatchedBrace)?;
+            let placeholder = &brace[1..end];
+            if placeholder.contains('{') {
+                return Err(PatternError::UnmatchedBrace);
+            }
+            let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+            let field = Field::from_name(name).ok_or_else(|| PatternError::UnknownPlaceholder {
+                name: name.to_string(),
+            })?;
+            let spec = Spec::parse(spec).ok_or_else(|| PatternError::InvalidSpec {
+                placeholder: placeholder.to_string(),
+            })?;
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Field { field, spec });
+            rest = &brace[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+}
+
+impl Formatter for Pattern {
+    fn format(&self, buf: &mut String, record: &Record, context: &Context) -> fmt::Result {
+        for segment in &self.segments {
+            let (field, spec) = match segment {
+                Segment::Literal(literal) => {
+                    buf.push_str(literal);
+                    continue;
+                }
+                Segment::Field { field, spec } => (field, spec),
+            };
+            match field {
+                Field::Time if context.colored() => {
+                    buf.push_str(DIM);
+                    spec.write(buf, context.time())?;
+                    buf.push_str(RESET);
+                }
+                Field::Time => spec.write(buf, context.time())?,
+                Field::Elapsed if context.colored() => {
+                    buf.push_str(DIM);
+                    spec.write(buf, Elapsed(context.elapsed()))?;
+                    buf.push_str(RESET);
+                }
+                Field::Elapsed => spec.write(buf, Elapsed(context.elapsed()))?,
+                Field::Level if context.colored() => {
+                    buf.push_str(level_style(record.level()));
+                    spec.write(buf, record.level())?;
+                    buf.push_str(RESET);
+                }
+                Field::Level => spec.write(buf, record.level())?,
+                Field::Thread => spec.write(buf, context.thread_name().unwrap_or("<unnamed>"))?,
+                Field::ThreadId => spec.write(buf, format_args!("{:?}", context.thread_id()))?,
+                Field::Target => spec.write(buf, record.target())?,
+                Field::ModulePath => spec.write(buf, record.module_path().unwrap_or(""))?,
+                Field::File => spec.write(buf, record.file().unwrap_or(""))?,
+                Field::Line => match record.line() {
+                    Some(line) => spec.write(buf, line)?,
+                    None => spec.write(buf, "")?,
+                },
+                Field::Message => spec.write(buf, record.args())?,
+                Field::Kv => spec.write(buf, TextKvs(record.key_values()))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorMode;
+    use crate::format::{Layout, LineBuffer, SharedFormatter};
+    use crate::timestamp::{Now, Timezone};
+    use log::Level;
+    use std::thread;
+    use std::time::Instant;
+
+    /// `template` applied to an `INFO` record of `app::db` logging `message`,
+    /// without the trailing newline.
+    fn format(template: &str, message: &str) -> String {
+        let mut layout = Layout::new(&Vec::<u8>::new());
+        layout.formatter = SharedFormatter::new(Pattern::parse(template).unwrap());
+        layout.color = layout.color.mode(ColorMode::Never);
+        let now = Now::new(Timezone::Utc, Instant::now());
+        let mut buffer = LineBuffer::default();
+        let line = buffer.format(
+            &layout,
+            &Record::builder()
+                .level(Level::Info)
+                .target("app::db")
+                .args(format_args!("{message}"))
+                .build(),
+            now,
+            &thread::current(),
+        );
+        line.strip_suffix('\n').unwrap().to_string()
+    }
+
+    fn error(template: &str) -> PatternError {
+        Pattern::parse(template).expect_err(template)
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(format("{{{level}}} }}{{", "m"), "{INFO} }{");
+        assert_eq!(format("{{message}}", "m"), "{message}");
+        assert_eq!(format("", "m"), "");
+    }
+
+    #[test]
+    fn rejects_unmatched_braces() {
+        for template in ["{level", "level}", "{lev{el}", "{", "}", "{{level}"] {
+            assert_eq!(error(template), PatternError::UnmatchedBrace, "{template}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        assert_eq!(
+            error("{x:*^10}"),
+            PatternError::UnknownPlaceholder {
+                name: "x".to_string()
+            }
+        );
+        assert_eq!(
+            error("{}"),
+            PatternError::UnknownPlaceholder {
+                name: String::new()
+            }
+        );
+        assert_eq!(
+            error("{Level}"),
+            PatternError::UnknownPlaceholder {
+                name: "Level".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        for template in [
+            "{level:>x}",
+            "{level:5x}",
+            "{level:*}",
+            "{level:>-5}",
+            "{level:+5}",
+        ] {
+            let placeholder = template[1..template.len() - 1].to_string();
+            assert_eq!(
+                error(template),
+                PatternError::InvalidSpec { placeholder },
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn pads_to_width() {
+        assert_eq!(format("[{level:>5}]", "m"), "[ INFO]");
+        assert_eq!(format("[{level:<6}]", "m"), "[INFO  ]");
+        assert_eq!(format("[{level:6}]", "m"), "[INFO  ]");
+        assert_eq!(format("[{message:^5}]", "ab"), "[ ab  ]");
+        assert_eq!(format("[{level:*^10}]", "m"), "[***INFO***]");
+        assert_eq!(format("[{level:0>3}]", "m"), "[INFO]");
+        assert_eq!(format("[{target:-<10}]", "m"), "[app::db---]");
+    }
+
+    #[test]
+    fn fill_before_alignment() {
+        // As in `std::fmt`, `5>` is `5` as fill, right aligned, without width.
+        assert_eq!(format("[{level:5>}]", "m"), "[INFO]");
+        assert_eq!(format("[{level:5>6}]", "m"), "[55INFO]");
+        // An alignment character can itself be the fill.
+        assert_eq!(format("[{level:>>6}]", "m"), "[>>INFO]");
+    }
+
+    #[test]
+    fn counts_width_in_characters() {
+        assert_eq!(format("[{message:é>4}]", "ab"), "[ééab]");
+        assert_eq!(format("[{message:🦀^6}]", "日本"), "[🦀🦀日本🦀🦀]");
+        assert_eq!(format("[{message:>4}]", "日本"), "[  日本]");
+        assert_eq!(format("[{message:>2}]", "日本語"), "[日本語]");
+    }
+}
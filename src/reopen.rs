@@ -0,0 +1,240 @@
+//! Log files that are reopened after being moved away by another program.
+
+use crate::rotate::open_append;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often to check whether the path still refers to the open file.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A log file that is reopened at its path after it was renamed or deleted,
+/// as happens when `logrotate` rotates it.
+///
+/// The file is reopened:
+///
+/// - when requested with [`ReopenHandle::reopen`],
+/// - when the path no longer refers to the open file, which is checked at most
+///   once per second (Unix only),
+/// - on `SIGHUP`, after calling [`ReopenHandle::reopen_on_sighup`] (requires
+///   the `signal` feature, Unix only).
+///
+/// Reopening only ever happens between two lines and the new file is opened
+/// before the old one is closed, so no line is lost or split across files.
+/// Should reopening fail, logging continues to the old file.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::{new_log, ReopenableFile};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let log_path = std::env::temp_dir().join("logout-doctest-reopen.log");
+///     let file = ReopenableFile::open(&log_path)?;
+///     let handle = file.handle();
+///     new_log()
+///       .sink(file)
+///       .enable()?;
+///     // After the file was moved away:
+///     handle.reopen();
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReopenableFile {
+    path: PathBuf,
+    file: File,
+    requested: Arc<AtomicBool>,
+    last_check: Instant,
+    line_start: bool,
+}
+
+impl ReopenableFile {
+    /// Open `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        Ok(Self {
+            path,
+            file,
+            requested: Arc::new(AtomicBool::new(false)),
+            last_check: Instant::now(),
+            line_start: true,
+        })
+    }
+
+    /// A handle to have the file reopened once it is owned by the logger.
+    #[must_use]
+    pub fn handle(&self) -> ReopenHandle {
+        ReopenHandle(Arc::clone(&self.requested))
+    }
+
+    fn reopen_if_needed(&mut self) {
+        if !self.requested.swap(false, Ordering::Relaxed) {
+            if self.last_check.elapsed() < CHECK_INTERVAL {
+                return;
+            }
+            self.last_check = Instant::now();
+            if !self.moved() {
+                return;
+            }
+        }
+        match open_append(&self.path) {
+            Ok(file) => {
+                let _ = self.file.flush();
+                self.file = file;
+            }
+            Err(e) => eprintln!("error reopening {}: {e}", self.path.display()),
+        }
+    }
+
+    /// Whether the path no longer refers to the open file.
+    #[cfg(unix)]
+    fn moved(&self) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        match (std::fs::metadata(&self.path), self.file.metadata()) {
+            (Ok(path), Ok(file)) => (path.dev(), path.ino()) != (file.dev(), file.ino()),
+            (Err(e), _) => e.kind() == io::ErrorKind::NotFound,
+            (Ok(_), Err(_)) => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn moved(&self) -> bool {
+        false
+    }
+}
+
+impl Write for ReopenableFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.line_start {
+            self.reopen_if_needed();
+        }
+        // Write everything so that a partial write can never leave half a line
+        // behind to be completed after reopening.
+        self.file.write_all(buf)?;
+        if let Some(&last) = buf.last() {
+            self.line_start = last == b'\n';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Has a [`ReopenableFile`] reopened before the next line is written.
+#[derive(Clone, Debug)]
+pub struct ReopenHandle(Arc<AtomicBool>);
+
+impl ReopenHandle {
+    /// Reopen the file before writing the next line.
+    pub fn reopen(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Reopen the file whenever the process receives `SIGHUP`, as sent by the
+    /// `postrotate` script of many `logrotate` configurations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal handler cannot be installed.
+    #[cfg(all(feature = "signal", unix))]
+    pub fn reopen_on_sighup(&self) -> Result<(), io::Error> {
+        signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&self.0)).map(|_id| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotate::suffixed;
+    use std::fs;
+
+    /// An empty directory for `test` to write to.
+    fn test_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("logout-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reopens_after_file_is_moved() {
+        let dir = test_dir("reopen-moved");
+        let path = dir.join("app.log");
+        let mut file = ReopenableFile::open(&path).unwrap();
+        file.write_all(b"line0\n").unwrap();
+        fs::rename(&path, suffixed(&path, 1)).unwrap();
+        // Not checked again within a second of the last check.
+        file.write_all(b"line1\n").unwrap();
+        assert!(!path.exists());
+
+        file.last_check -= CHECK_INTERVAL;
+        file.write_all(b"line2\n").unwrap();
+        assert_eq!(read(&path), "line2\n");
+        assert_eq!(read(&suffixed(&path, 1)), "line0\nline1\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reopens_on_request() {
+        let dir = test_dir("reopen-request");
+        let path = dir.join("app.log");
+        let mut file = ReopenableFile::open(&path).unwrap();
+        let handle = file.handle();
+        file.write_all(b"line0\n").unwrap();
+        fs::rename(&path, suffixed(&path, 1)).unwrap();
+        handle.reopen();
+        file.write_all(b"line1\n").unwrap();
+        assert_eq!(read(&path), "line1\n");
+        assert_eq!(read(&suffixed(&path, 1)), "line0\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn never_reopens_within_a_line() {
+        let dir = test_dir("reopen-line");
+        let path = dir.join("app.log");
+        let mut file = ReopenableFile::open(&path).unwrap();
+        let handle = file.handle();
+        file.write_all(b"part").unwrap();
+        fs::rename(&path, suffixed(&path, 1)).unwrap();
+        handle.reopen();
+        file.last_check -= CHECK_INTERVAL;
+        file.write_all(b"ial\n").unwrap();
+        assert!(!path.exists());
+        file.write_all(b"next\n").unwrap();
+        assert_eq!(read(&suffixed(&path, 1)), "partial\n");
+        assert_eq!(read(&path), "next\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_writing_while_reopening_fails() {
+        let dir = test_dir("reopen-fails");
+        let path = dir.join("app.log");
+        let mut file = ReopenableFile::open(&path).unwrap();
+        let handle = file.handle();
+        fs::rename(&path, suffixed(&path, 1)).unwrap();
+        fs::create_dir(&path).unwrap();
+        handle.reopen();
+        file.write_all(b"line0\n").unwrap();
+        assert_eq!(read(&suffixed(&path, 1)), "line0\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
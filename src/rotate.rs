@@ -0,0 +1,656 @@
+//! Log files that are rolled over as they grow or as time passes.
+
+use crate::compress::{Compression, Compressor, EXTENSIONS};
+use crate::timestamp::Timezone;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use time::{Date, Duration, Month, OffsetDateTime, Time};
+
+/// A log file that is rolled over once it grows beyond `max_size` bytes.
+///
+/// On rollover `name` is renamed to `name.1`, `name.1` to `name.2` and so on,
+/// keeping at most `max_archives` archives; the oldest is deleted. A rollover
+/// only ever happens between two lines, so lines are never split across files
+/// and a single line longer than `max_size` still ends up whole in one file.
+///
+/// Should a rollover fail, for instance for lack of file descriptors, lines
+/// keep going to the file being rolled over and the rollover is retried. A retry
+/// picks up where the failed attempt stopped, so each archive is shifted only
+/// once per rollover and retrying never deletes any.
+/// With `max_archives` set to 0 the file is emptied in place instead.
+///
+/// Archives can be [compressed](RotatingFile::compression) in the background,
+/// becoming `name.1.gz` and so on. Should the previous archive still be
+/// compressing when the next rollover is due, the rollover waits for it.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::{new_log, RotatingFile};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let log_path = std::env::temp_dir().join("logout-doctest-rotating.log");
+///     // Roll over every 10 MiB, keeping `sim.log.1` to `sim.log.5`.
+///     new_log()
+///       .sink(RotatingFile::open(&log_path, 10 * 1024 * 1024, 5)?)
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_archives: usize,
+    compressor: Compressor,
+    // Whether the last byte written ended a line, i.e. whether rolling over now
+    // would not split a line.
+    line_start: bool,
+    // How far a failed rollover got.
+    rollover: Rollover,
+}
+
+/// Progress of a [`RotatingFile`] rollover, kept when an attempt fails.
+#[derive(Debug)]
+enum Rollover {
+    /// No rollover under way.
+    Idle,
+    /// The oldest archive is gone and the archives from this one up have been
+    /// shifted, leaving it free. Those below it are still to be shifted.
+    Shifting(usize),
+    /// `name.1`, still being written to, after renaming `name` to it but
+    /// failing to open a new `name`.
+    Archived(PathBuf),
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, rolling it over once it exceeds `max_size`
+    /// bytes and keeping at most `max_archives` rolled over files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(
+        path: impl AsRef<Path>,
+        max_size: u64,
+        max_archives: usize,
+    ) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            max_archives,
+            compressor: Compressor::new(Compression::None),
+            line_start: true,
+            rollover: Rollover::Idle,
+        })
+    }
+
+    /// Compress archives after rolling over.
+    #[must_use]
+    pub fn compression(self, compression: Compression) -> Self {
+        Self {
+            compressor: Compressor::new(compression),
+            ..self
+        }
+    }
+
+    /// Roll over, or report why not and carry on with the current file.
+    fn try_rotate(&mut self) {
+        let retrying = !matches!(self.rollover, Rollover::Idle);
+        if let Err(e) = self.rotate() {
+            if !retrying {
+                eprintln!(
+                    "error rolling over {}, writing to the current file: {e}",
+                    self.path.display()
+                );
+            }
+            if let Rollover::Idle = self.rollover {
+                // Removing the oldest archive failed, so wait for another
+                // `max_size` bytes rather than retry with the next line.
+                self.size = 0;
+            }
+        }
+    }
+
+    fn rotate(&mut self) -> Result<(), io::Error> {
+        self.file.flush()?;
+        if self.max_archives == 0 {
+            // Unlike reopening, this cannot fail for lack of file descriptors.
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        if let Rollover::Idle = self.rollover {
+            // `name.1` must have been compressed before it can be shifted.
+            self.compressor.wait();
+            for archive in self.archives(self.max_archives) {
+                if archive.exists() {
+                    fs::remove_file(archive)?;
+                }
+            }
+            self.rollover = Rollover::Shifting(self.max_archives);
+        }
+        if let Rollover::Shifting(free) = self.rollover {
+            for n in (1..free).rev() {
+                for (from, to) in self.archives(n).zip(self.archives(n + 1)) {
+                    if from.exists() {
+                        fs::rename(from, to)?;
+                    }
+                }
+                self.rollover = Rollover::Shifting(n);
+            }
+            let archive = suffixed(&self.path, 1);
+            fs::rename(&self.path, &archive)?;
+            self.rollover = Rollover::Archived(archive);
+        }
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        if let Rollover::Archived(archive) = mem::replace(&mut self.rollover, Rollover::Idle) {
+            self.compressor.spawn(archive);
+        }
+        Ok(())
+    }
+
+    /// Paths archive `n` can have, uncompressed or compressed with any
+    /// compression. An archive stays uncompressed if compressing it failed.
+    fn archives(&self, n: usize) -> impl Iterator<Item = PathBuf> {
+        let archive = suffixed(&self.path, n);
+        let compressed = EXTENSIONS.map(|ext| suffixed(&archive, ext));
+        std::iter::once(archive).chain(compressed)
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len() as u64;
+        if self.line_start
+            && (!matches!(self.rollover, Rollover::Idle)
+                || self.size > 0 && self.size + len > self.max_size)
+        {
+            self.try_rotate();
+        }
+        // Write everything so that a partial write can never leave half a line
+        // behind to be completed after a rollover.
+        self.file.write_all(buf)?;
+        self.size += len;
+        if let Some(&last) = buf.last() {
+            self.line_start = last == b'\n';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// How often a [`TimedFile`] starts a new file.
+#[derive(Copy, Clone, Debug)]
+pub enum Period {
+    /// At the start of every hour, naming files `name.YYYY-MM-DD-HH`.
+    Hourly,
+    /// At midnight, naming files `name.YYYY-MM-DD`.
+    Daily,
+}
+
+impl Period {
+    /// Start of the period containing `time`.
+    fn start(self, time: OffsetDateTime) -> OffsetDateTime {
+        let midnight = time.replace_time(Time::MIDNIGHT);
+        match self {
+            Self::Hourly => midnight + Duration::hours(i64::from(time.hour())),
+            Self::Daily => midnight,
+        }
+    }
+
+    fn length(self) -> Duration {
+        match self {
+            Self::Hourly => Duration::HOUR,
+            Self::Daily => Duration::DAY,
+        }
+    }
+
+    fn suffix(self, start: OffsetDateTime) -> String {
+        let (year, month, day) = start.to_calendar_date();
+        let date = format!("{year:04}-{:02}-{day:02}", u8::from(month));
+        match self {
+            Self::Hourly => format!("{date}-{:02}", start.hour()),
+            Self::Daily => date,
+        }
+    }
+
+    /// Date and hour encoded in a suffix written by [`Period::suffix`].
+    fn parse_suffix(self, suffix: &str) -> Option<(Date, u8)> {
+        let parts = suffix
+            .split('-')
+            .map(|part| {
+                part.parse::<u16>()
+                    .ok()
+                    .filter(|_| part.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let (year, month, day, hour) = match (self, parts.as_slice()) {
+            (Self::Hourly, &[year, month, day, hour]) => (year, month, day, hour),
+            (Self::Daily, &[year, month, day]) => (year, month, day, 0),
+            _ => return None,
+        };
+        let month = Month::try_from(u8::try_from(month).ok()?).ok()?;
+        let date = Date::from_calendar_date(year.into(), month, u8::try_from(day).ok()?).ok()?;
+        Some((date, u8::try_from(hour).ok().filter(|h| *h < 24)?))
+    }
+}
+
+/// A log file that starts afresh at every hour or day boundary.
+///
+/// Lines are written to `name.<timestamp>`, where the timestamp is the start of
+/// the current [`Period`] in local time, or in the time zone given to
+/// [`TimedFile::open_with_timezone`]. `Logger::to_timed_file` uses the time
+/// zone of the logger. As with [`RotatingFile`], a new file is only ever
+/// started between two lines, and should starting it fail, lines keep going to
+/// the previous file while it is retried.
+///
+/// When `max_age_days` is set, files of this logger older than that many days
+/// are deleted whenever a new file is started. Deletion is best effort, a file
+/// that cannot be removed does not stop logging. Compressed files count too,
+/// whatever the current [compression](TimedFile::compression).
+///
+/// Finished files can be [compressed](TimedFile::compression) in the
+/// background.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::{new_log, Period, TimedFile};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// #   let log_path = std::env::temp_dir().join("logout-doctest-timed.log");
+///     // One file per day, keeping a week's worth.
+///     new_log()
+///       .sink(TimedFile::open(&log_path, Period::Daily, Some(7))?)
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TimedFile {
+    path: PathBuf,
+    period: Period,
+    max_age_days: Option<u32>,
+    compressor: Compressor,
+    file: File,
+    timezone: Timezone,
+    start: OffsetDateTime,
+    next_rollover: OffsetDateTime,
+    line_start: bool,
+    // Whether the last rollover failed and has been reported.
+    retrying: bool,
+}
+
+impl TimedFile {
+    /// Open the file for the current period of `path`, deleting files older
+    /// than `max_age_days` if given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(
+        path: impl AsRef<Path>,
+        period: Period,
+        max_age_days: Option<u32>,
+    ) -> Result<Self, io::Error> {
+        Self::open_with_timezone(path, period, max_age_days, Timezone::Local)
+    }
+
+    /// Like [`TimedFile::open`], with periods starting and files named in
+    /// `timezone` rather than in local time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open_with_timezone(
+        path: impl AsRef<Path>,
+        period: Period,
+        max_age_days: Option<u32>,
+        timezone: Timezone,
+    ) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let start = period.start(timezone.now());
+        let file = open_append(&suffixed(&path, period.suffix(start)))?;
+        let timed = Self {
+            path,
+            period,
+            max_age_days,
+            compressor: Compressor::new(Compression::None),
+            file,
+            timezone,
+            start,
+            next_rollover: start + period.length(),
+            line_start: true,
+            retrying: false,
+        };
+        timed.remove_expired();
+        Ok(timed)
+    }
+
+    /// Compress files once their period is over.
+    #[must_use]
+    pub fn compression(self, compression: Compression) -> Self {
+        Self {
+            compressor: Compressor::new(compression),
+            ..self
+        }
+    }
+
+    /// Start a new file, or report why not and carry on with the current one.
+    fn try_rotate(&mut self) {
+        match self.rotate() {
+            Ok(()) => self.retrying = false,
+            Err(e) => {
+                if !self.retrying {
+                    eprintln!(
+                        "error starting a new file for {}, writing to the current file: {e}",
+                        self.path.display()
+                    );
+                }
+                self.retrying = true;
+            }
+        }
+    }
+
+    fn rotate(&mut self) -> Result<(), io::Error> {
+        self.file.flush()?;
+        let start = self.period.start(self.timezone.now());
+        let previous = suffixed(&self.path, self.period.suffix(self.start));
+        let current = suffixed(&self.path, self.period.suffix(start));
+        if current == previous {
+            // The local offset changed without moving us into a new period.
+            self.next_rollover = start + self.period.length();
+            return Ok(());
+        }
+        // Until the new file is open, keep rolling over with every line.
+        self.file = open_append(&current)?;
+        self.start = start;
+        self.next_rollover = start + self.period.length();
+        self.compressor.spawn(previous);
+        self.remove_expired();
+        Ok(())
+    }
+
+    fn remove_expired(&self) {
+        let Some(days) = self.max_age_days else {
+            return;
+        };
+        let cutoff = self.start - Duration::days(i64::from(days));
+        let cutoff = (cutoff.date(), cutoff.hour());
+
+        let Some(name) = self.path.file_name().and_then(|n| n.to_str()) else {
+            return;
+        };
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let expired = file_name
+                .to_str()
+                .and_then(|f| f.strip_prefix(name))
+                .and_then(|f| f.strip_prefix('.'))
+                .map(|suffix| {
+                    EXTENSIONS
+                        .iter()
+                        .find_map(|ext| suffix.strip_suffix(ext)?.strip_suffix('.'))
+                        .unwrap_or(suffix)
+                })
+                .and_then(|suffix| self.period.parse_suffix(suffix))
+                .is_some_and(|stamp| stamp < cutoff);
+            if expired {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+impl Write for TimedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.line_start && OffsetDateTime::now_utc() >= self.next_rollover {
+            self.try_rotate();
+        }
+        self.file.write_all(buf)?;
+        if let Some(&last) = buf.last() {
+            self.line_start = last == b'\n';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+pub(crate) fn open_append(path: &Path) -> Result<File, io::Error> {
+    OpenOptions::new().append(true).create(true).open(path)
+}
+
+/// `path` with `.suffix` appended to the file name.
+pub(crate) fn suffixed(path: &Path, suffix: impl std::fmt::Display) -> PathBuf {
+    let mut archive = OsString::from(path);
+    archive.push(format!(".{suffix}"));
+    archive.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory for `test` to write to.
+    fn test_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("logout-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotating_file_shifts_and_keeps_max_archives() {
+        let dir = test_dir("rotating-shift");
+        let path = dir.join("app.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for i in 0..4 {
+            file.write_all(format!("line{i}\n").as_bytes()).unwrap();
+        }
+        assert_eq!(read(&path), "line3\n");
+        assert_eq!(read(&suffixed(&path, 1)), "line2\n");
+        assert_eq!(read(&suffixed(&path, 2)), "line1\n");
+        assert!(!suffixed(&path, 3).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotating_file_never_splits_lines() {
+        let dir = test_dir("rotating-lines");
+        let path = dir.join("app.log");
+        let mut file = RotatingFile::open(&path, 10, 1).unwrap();
+        file.write_all(b"a much longer line\n").unwrap();
+        file.write_all(b"part").unwrap();
+        file.write_all(b"ial\n").unwrap();
+        assert_eq!(read(&suffixed(&path, 1)), "a much longer line\n");
+        assert_eq!(read(&path), "partial\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotating_file_without_archives_starts_over() {
+        let dir = test_dir("rotating-none");
+        let path = dir.join("app.log");
+        let mut file = RotatingFile::open(&path, 10, 0).unwrap();
+        for i in 0..3 {
+            file.write_all(format!("line{i}\n").as_bytes()).unwrap();
+        }
+        assert_eq!(read(&path), "line2\n");
+        assert!(!suffixed(&path, 1).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotating_file_keeps_writing_while_reopening_fails() {
+        let dir = test_dir("rotating-reopen");
+        let path = dir.join("app.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_all(b"line0\n").unwrap();
+        // As if a rollover had renamed `app.log` and then failed to open a new
+        // one, which a directory in the way keeps failing.
+        fs::rename(&path, suffixed(&path, 1)).unwrap();
+        file.rollover = Rollover::Archived(suffixed(&path, 1));
+        fs::create_dir(&path).unwrap();
+        file.write_all(b"line1\n").unwrap();
+        file.write_all(b"line2\n").unwrap();
+        assert_eq!(read(&suffixed(&path, 1)), "line0\nline1\nline2\n");
+        assert!(!suffixed(&path, 2).exists());
+
+        fs::remove_dir(&path).unwrap();
+        file.write_all(b"line3\n").unwrap();
+        assert_eq!(read(&path), "line3\n");
+        assert_eq!(read(&suffixed(&path, 1)), "line0\nline1\nline2\n");
+        assert!(!suffixed(&path, 2).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotating_file_shifts_archives_once_while_renaming_fails() {
+        let dir = test_dir("rotating-rename");
+        let path = dir.join("app.log");
+        fs::write(suffixed(&path, 1), "old\n").unwrap();
+        let mut file = RotatingFile::open(&path, 10, 3).unwrap();
+        file.write_all(b"line0\n").unwrap();
+        // As if a rollover had shifted the archives and then failed to rename
+        // `app.log`, which a directory in the way keeps failing.
+        fs::rename(suffixed(&path, 1), suffixed(&path, 2)).unwrap();
+        file.rollover = Rollover::Shifting(1);
+        fs::create_dir(suffixed(&path, 1)).unwrap();
+        file.write_all(b"line1\n").unwrap();
+        file.write_all(b"line2\n").unwrap();
+        assert_eq!(read(&path), "line0\nline1\nline2\n");
+        assert_eq!(read(&suffixed(&path, 2)), "old\n");
+        assert!(!suffixed(&path, 3).exists());
+
+        fs::remove_dir(suffixed(&path, 1)).unwrap();
+        file.write_all(b"line3\n").unwrap();
+        assert_eq!(read(&path), "line3\n");
+        assert_eq!(read(&suffixed(&path, 1)), "line0\nline1\nline2\n");
+        assert_eq!(read(&suffixed(&path, 2)), "old\n");
+        assert!(!suffixed(&path, 3).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotating_file_keeps_writing_while_archiving_fails() {
+        let dir = test_dir("rotating-archive");
+        let path = dir.join("app.log");
+        // An archive that cannot be removed keeps the rollover from happening.
+        fs::create_dir_all(suffixed(&path, 1).join("keep")).unwrap();
+        let mut file = RotatingFile::open(&path, 10, 1).unwrap();
+        for i in 0..3 {
+            file.write_all(format!("line{i}\n").as_bytes()).unwrap();
+        }
+        assert_eq!(read(&path), "line0\nline1\nline2\n");
+        assert!(suffixed(&path, 1).join("keep").is_dir());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotating_file_shifts_archives_compressed_earlier() {
+        let dir = test_dir("rotating-compressed");
+        let path = dir.join("app.log");
+        fs::write(suffixed(&path, "1.gz"), "gzip").unwrap();
+        fs::write(suffixed(&path, "2.zst"), "zstd").unwrap();
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_all(b"line0\n").unwrap();
+        file.write_all(b"line1\n").unwrap();
+        assert_eq!(read(&suffixed(&path, "2.gz")), "gzip");
+        assert!(!suffixed(&path, "2.zst").exists());
+        assert!(!suffixed(&path, "3.zst").exists());
+        assert_eq!(read(&suffixed(&path, 1)), "line0\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn timed_file_keeps_writing_while_opening_fails() {
+        let dir = test_dir("timed-reopen");
+        let path = dir.join("app.log");
+        let mut file = TimedFile::open(&path, Period::Daily, None).unwrap();
+        let current = suffixed(&path, Period::Daily.suffix(file.start));
+        // As if the file had been opened yesterday, with a directory in the
+        // way of today's.
+        file.start -= Period::Daily.length();
+        file.next_rollover -= Period::Daily.length();
+        let previous = suffixed(&path, Period::Daily.suffix(file.start));
+        fs::rename(&current, &previous).unwrap();
+        fs::create_dir(&current).unwrap();
+        file.write_all(b"line0\n").unwrap();
+        file.write_all(b"line1\n").unwrap();
+        assert_eq!(read(&previous), "line0\nline1\n");
+
+        fs::remove_dir(&current).unwrap();
+        file.write_all(b"line2\n").unwrap();
+        assert_eq!(read(&current), "line2\n");
+        assert_eq!(read(&previous), "line0\nline1\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn timed_file_removes_expired_files_compressed_or_not() {
+        let dir = test_dir("timed-expired");
+        let path = dir.join("app.log");
+        let expired = ["2020-01-01", "2020-01-02.gz", "2020-01-03.zst"];
+        let kept = ["2020-01-04.bz2", "notes", "2020-01-05.gz.tmp"];
+        for suffix in expired.iter().chain(&kept) {
+            fs::write(suffixed(&path, suffix), suffix).unwrap();
+        }
+        let file = TimedFile::open(&path, Period::Daily, Some(7)).unwrap();
+        for suffix in expired {
+            assert!(!suffixed(&path, suffix).exists(), "{suffix}");
+        }
+        for suffix in kept {
+            assert!(suffixed(&path, suffix).exists(), "{suffix}");
+        }
+        assert!(suffixed(&path, Period::Daily.suffix(file.start)).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn timed_file_names_files_in_its_time_zone() {
+        let dir = test_dir("timed-timezone");
+        for (name, hours) in [("east.log", 14), ("west.log", -12)] {
+            let path = dir.join(name);
+            let timezone = Timezone::Fixed(time::UtcOffset::from_hms(hours, 0, 0).unwrap());
+            let before = Period::Hourly.start(timezone.now());
+            let _logger = crate::new_log()
+                .timezone(timezone)
+                .to_timed_file(&path, Period::Hourly, None)
+                .unwrap();
+            let after = Period::Hourly.start(timezone.now());
+            let opened =
+                [before, after].map(|start| suffixed(&path, Period::Hourly.suffix(start)).exists());
+            assert!(opened.contains(&true), "{name}");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
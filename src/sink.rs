@@ -0,0 +1,408 @@
+//! Destinations lines are written to.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, PipeWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// A writer that writes each line with a single system call, so that lines
+/// written concurrently by several threads are never interleaved. Such a sink
+/// is written to without holding a lock, see `Logger::atomic_sink`.
+///
+/// This holds for:
+///
+/// - Files opened in append mode, as by `Logger::to_file` and [`Output::file`](crate::Output::file),
+///   on local file systems. Files opened otherwise may overwrite each other's lines.
+/// - Pipes, as long as lines are no longer than `PIPE_BUF` (at least 512
+///   bytes, 4096 on Linux). Longer lines may be interleaved.
+///
+/// If the system call writes only part of a line, as on a full disk, the rest
+/// is not written with another call, which could land in the middle of another
+/// thread's line. The write fails with [`io::ErrorKind::WriteZero`] instead.
+pub trait AtomicWrite: Send + Sync {
+    /// Write `line`, which ends with a newline, as a whole.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn write_line(&self, line: &[u8]) -> io::Result<()>;
+
+    /// Flush anything buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if flushing fails.
+    fn flush(&self) -> io::Result<()>;
+}
+
+/// Write `line` with a single `write` call, retried only if interrupted.
+fn write_once(mut writer: impl Write, line: &[u8]) -> io::Result<()> {
+    loop {
+        match writer.write(line) {
+            Ok(n) if n == line.len() => return Ok(()),
+            Ok(n) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    format!("wrote {n} of {} bytes", line.len()),
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl AtomicWrite for File {
+    fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        write_once(self, line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Write::flush(&mut &*self)
+    }
+}
+
+impl AtomicWrite for PipeWriter {
+    fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        write_once(self, line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Write::flush(&mut &*self)
+    }
+}
+
+/// What to do when writing a line to a sink fails. Set with
+/// `Logger::on_write_error`.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::{new_log, WriteErrorPolicy};
+///
+/// # fn alert(_: &std::io::Error) {}
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .on_write_error(WriteErrorPolicy::callback(|e| alert(e)))
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum WriteErrorPolicy {
+    /// Write the line to stderr instead. The error is reported on stderr as
+    /// well, at most once per second.
+    #[default]
+    Stderr,
+    /// Discard the line, silently.
+    Drop,
+    /// Panic on the thread writing the line. In
+    /// [asynchronous](crate::OverflowPolicy) mode that is the writer thread,
+    /// after which the logger writes synchronously, so that the next failure
+    /// panics on the thread logging the line.
+    Panic,
+    /// Try writing the line up to this many more times, then write it to
+    /// stderr as with [`WriteErrorPolicy::Stderr`].
+    Retry(u32),
+    /// Pass the error to a callback and discard the line. Created with
+    /// [`WriteErrorPolicy::callback`].
+    Callback(Arc<dyn Fn(&io::Error) + Send + Sync>),
+}
+
+impl WriteErrorPolicy {
+    /// Pass write errors to `callback` and discard the lines that failed.
+    pub fn callback(callback: impl Fn(&io::Error) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for WriteErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stderr => f.write_str("Stderr"),
+            Self::Drop => f.write_str("Drop"),
+            Self::Panic => f.write_str("Panic"),
+            Self::Retry(retries) => f.debug_tuple("Retry").field(retries).finish(),
+            Self::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+/// Number of lines that could not be written to their sink, whatever the
+/// [`WriteErrorPolicy`] then did with them.
+///
+/// Obtained from `Logger::failed_writes` and kept up to date after the logger
+/// is enabled.
+#[derive(Clone, Debug)]
+pub struct FailedWrites(Arc<AtomicU64>);
+
+impl FailedWrites {
+    /// Number of lines that failed to be written so far.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Applies the write error policy of a logger to all its sinks.
+#[derive(Debug)]
+pub(crate) struct WriteErrors {
+    policy: WriteErrorPolicy,
+    failed: Arc<AtomicU64>,
+    start: Instant,
+    // Milliseconds after `start` at which the next error may be reported.
+    next_report: AtomicU64,
+    suppressed: AtomicU64,
+}
+
+impl WriteErrors {
+    pub(crate) fn new(policy: WriteErrorPolicy) -> Self {
+        Self {
+            policy,
+            failed: Arc::new(AtomicU64::new(0)),
+            start: Instant::now(),
+            next_report: AtomicU64::new(0),
+            suppressed: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether a failed write panics.
+    pub(crate) fn panics(&self) -> bool {
+        matches!(self.policy, WriteErrorPolicy::Panic)
+    }
+
+    /// The same counter of failed writes, with another policy.
+    pub(crate) fn policy(&self, policy: WriteErrorPolicy) -> Self {
+        Self {
+            failed: Arc::clone(&self.failed),
+            ..Self::new(policy)
+        }
+    }
+
+    pub(crate) fn failed(&self) -> FailedWrites {
+        FailedWrites(Arc::clone(&self.failed))
+    }
+
+    fn handle(&self, e: &io::Error, line: &str) {
+        let _ = self.failed.fetch_add(1, Ordering::Relaxed);
+        match &self.policy {
+            WriteErrorPolicy::Stderr | WriteErrorPolicy::Retry(_) => {
+                self.report(e);
+                // Fallback write to stderr.
+                eprint!("{line}");
+            }
+            WriteErrorPolicy::Drop => {}
+            WriteErrorPolicy::Panic => panic!("error writing to sink: {e}"),
+            WriteErrorPolicy::Callback(callback) => callback(e),
+        }
+    }
+
+    /// Report `e` unless an error was reported less than a second ago.
+    fn report(&self, e: &io::Error) {
+        let now = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        let next = self.next_report.load(Ordering::Relaxed);
+        if now < next
+            || self
+                .next_report
+                .compare_exchange(
+                    next,
+                    now.saturating_add(1000),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+        {
+            let _ = self.suppressed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        match self.suppressed.swap(0, Ordering::Relaxed) {
+            0 => eprintln!("error writing to sink, falling back to stderr: {e}"),
+            suppressed => eprintln!(
+                "error writing to sink, falling back to stderr: {e} \
+                 ({suppressed} more errors since the last report)"
+            ),
+        }
+    }
+}
+
+/// A sink shared between the logger, the writer thread and the guard.
+#[derive(Clone)]
+pub(crate) enum Sink {
+    /// Written to under a lock.
+    Locked(Arc<Mutex<dyn Write + Send>>),
+    /// Written to without a lock.
+    Atomic(Arc<dyn AtomicWrite>),
+}
+
+impl Sink {
+    pub(crate) fn locked(writer: impl Write + Send + 'static) -> Self {
+        Self::Locked(Arc::new(Mutex::new(writer)))
+    }
+
+    pub(crate) fn atomic(writer: impl AtomicWrite + 'static) -> Self {
+        Self::Atomic(Arc::new(writer))
+    }
+
+    /// Write `line`, which ends with a newline, as a whole, handling a failure
+    /// as `errors` says.
+    pub(crate) fn write_line(&self, line: &str, errors: &WriteErrors) {
+        // Bytes of `line` already written, which a retry must not repeat.
+        let mut done = 0;
+        let mut written = self.try_write_line(line.as_bytes(), &mut done);
+        if let (Err(_), WriteErrorPolicy::Retry(retries)) = (&written, &errors.policy) {
+            for _ in 0..*retries {
+                written = self.try_write_line(line.as_bytes(), &mut done);
+                if written.is_ok() {
+                    break;
+                }
+            }
+        }
+        if let Err(e) = written {
+            errors.handle(&e, line);
+        }
+    }
+
+    /// Write what is left of `line` after the first `done` bytes, counting the
+    /// bytes written in `done`. An atomic sink writes the whole line or fails.
+    fn try_write_line(&self, line: &[u8], done: &mut usize) -> io::Result<()> {
+        match self {
+            Self::Locked(sink) => write_rest(&mut *lock(sink), line, done),
+            Self::Atomic(sink) => sink.write_line(line),
+        }
+    }
+
+    pub(crate) fn flush(&self) {
+        let flushed = match self {
+            Self::Locked(sink) => lock(sink).flush(),
+            Self::Atomic(sink) => sink.flush(),
+        };
+        if let Err(e) = flushed {
+            eprintln!("error flushing sink: {e}");
+        }
+    }
+}
+
+/// Write `line` from byte `done` on, like `write_all` but keeping count of the
+/// bytes written should it fail.
+fn write_rest(writer: &mut dyn Write, line: &[u8], done: &mut usize) -> io::Result<()> {
+    while *done < line.len() {
+        match writer.write(&line[*done..]) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => *done += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Lock `sink`, recovering it if a thread panicked while holding the lock. The
+/// poisoning is reported once, the sink keeps being written to.
+fn lock<'a>(
+    sink: &'a Mutex<dyn Write + Send + 'static>,
+) -> MutexGuard<'a, dyn Write + Send + 'static> {
+    sink.lock().unwrap_or_else(|poisoned| {
+        eprintln!("a thread panicked while writing to a log sink, continuing to log to it");
+        sink.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+impl fmt::Debug for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Locked(_) => f.write_str("Locked"),
+            Self::Atomic(_) => f.write_str("Atomic"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Takes at most `limit` bytes per call, after one interruption.
+    struct Short {
+        limit: usize,
+        interrupted: bool,
+        written: Vec<u8>,
+    }
+
+    impl Write for Short {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if !self.interrupted {
+                self.interrupted = true;
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let n = buf.len().min(self.limit);
+            self.written.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn short(limit: usize) -> Short {
+        Short {
+            limit,
+            interrupted: false,
+            written: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn writes_line_once() {
+        let mut writer = short(64);
+        write_once(&mut writer, b"line\n").unwrap();
+        assert_eq!(writer.written, b"line\n");
+    }
+
+    #[test]
+    fn short_write_fails() {
+        let mut writer = short(2);
+        let e = write_once(&mut writer, b"line\n").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(writer.written, b"li");
+    }
+
+    /// Takes half of the first write, then fails once.
+    struct Flaky {
+        calls: usize,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            let n = match self.calls {
+                1 => buf.len() / 2,
+                2 => return Err(io::ErrorKind::StorageFull.into()),
+                _ => buf.len(),
+            };
+            self.written.lock().unwrap().extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn retry_writes_only_the_rest_of_the_line() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let sink = Sink::locked(Flaky {
+            calls: 0,
+            written: Arc::clone(&written),
+        });
+        let errors = WriteErrors::new(WriteErrorPolicy::Retry(2));
+        sink.write_line("abcdefgh\n", &errors);
+        assert_eq!(*written.lock().unwrap(), b"abcdefgh\n");
+        assert_eq!(errors.failed.load(Ordering::Relaxed), 0);
+    }
+}
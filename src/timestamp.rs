@@ -0,0 +1,451 @@
+//! Time of log messages.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::num::NonZeroU8;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use time::format_description::well_known::iso8601::{
+    Config, EncodedConfig, Iso8601, TimePrecision,
+};
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::format_description::{self, OwnedFormatItem};
+use time::{OffsetDateTime, UtcOffset};
+
+const ISO8601_MILLIS: EncodedConfig = Config::DEFAULT
+    .set_time_precision(TimePrecision::Second {
+        decimal_digits: NonZeroU8::new(3),
+    })
+    .encode();
+const ISO8601_MICROS: EncodedConfig = Config::DEFAULT
+    .set_time_precision(TimePrecision::Second {
+        decimal_digits: NonZeroU8::new(6),
+    })
+    .encode();
+
+/// How the time of a log message is written.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum TimeFormat {
+    /// `Sat, 17 Oct 2026 13:45:07 +0200`
+    Rfc2822,
+    /// `2026-10-17T13:45:07.123456789+02:00`
+    Rfc3339,
+    /// ISO 8601 with milliseconds, `2026-10-17T13:45:07.123+02:00`
+    Iso8601Millis,
+    /// ISO 8601 with microseconds, `2026-10-17T13:45:07.123456+02:00`
+    Iso8601Micros,
+    /// Whole seconds since the Unix epoch, `1792237507`
+    UnixSeconds,
+    /// Nanoseconds since the Unix epoch, `1792237507123456789`
+    UnixNanos,
+    /// A format description created with [`TimeFormat::custom`].
+    Custom(CustomTimeFormat),
+}
+
+impl TimeFormat {
+    /// A format described in the
+    /// [`time` format description syntax](https://time-rs.github.io/book/api/format-description.html)
+    /// (version 2), such as `"[year]-[month]-[day] [hour]:[minute]:[second]"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `description` is not a valid format description.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use logout::{new_log, TimeFormat};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     new_log()
+    ///       .time_format(TimeFormat::custom("[hour]:[minute]:[second].[subsecond digits:3]")?)
+    ///       .enable()?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn custom(description: &str) -> Result<Self, TimeFormatError> {
+        let items = format_description::parse_owned::<2>(description).map_err(TimeFormatError)?;
+        Ok(Self::Custom(CustomTimeFormat(Arc::new(items))))
+    }
+
+    pub(crate) fn format_into(
+        &self,
+        buf: &mut Vec<u8>,
+        time: OffsetDateTime,
+    ) -> Result<(), time::error::Format> {
+        let _ = match self {
+            Self::Rfc2822 => time.format_into(buf, &Rfc2822)?,
+            Self::Rfc3339 => time.format_into(buf, &Rfc3339)?,
+            Self::Iso8601Millis => time.format_into(buf, &Iso8601::<ISO8601_MILLIS>)?,
+            Self::Iso8601Micros => time.format_into(buf, &Iso8601::<ISO8601_MICROS>)?,
+            Self::UnixSeconds => return write_display(buf, time.unix_timestamp()),
+            Self::UnixNanos => return write_display(buf, time.unix_timestamp_nanos()),
+            Self::Custom(custom) => time.format_into(buf, &*custom.0)?,
+        };
+        Ok(())
+    }
+}
+
+/// A parsed custom format description, see [`TimeFormat::custom`].
+#[derive(Clone, Debug)]
+pub struct CustomTimeFormat(Arc<OwnedFormatItem>);
+
+/// Error returned by [`TimeFormat::custom`] for an invalid format description.
+#[derive(Clone, Debug)]
+pub struct TimeFormatError(time::error::InvalidFormatDescription);
+
+impl fmt::Display for TimeFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid time format description: {}", self.0)
+    }
+}
+
+impl Error for TimeFormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Which clock the time of a log message is read from.
+///
+/// Elapsed time is measured with a monotonic clock from the moment the logger
+/// was created with `new_log`, and written as `+12.345678s`.
+///
+/// # Examples
+///
+/// ```rust
+/// use logout::{new_log, Clock};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     new_log()
+///       .clock(Clock::Elapsed)
+///       .enable()?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Clock {
+    /// Wall-clock time, in the configured time format and time zone.
+    #[default]
+    WallClock,
+    /// Time elapsed since the logger was created, `+12.345678s`.
+    Elapsed,
+    /// Wall-clock time followed by the elapsed time,
+    /// `Sat, 17 Oct 2026 13:45:07 +0200 +12.345678s`.
+    Both,
+}
+
+/// The moment a record was logged, read from both clocks.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Now {
+    pub(crate) time: OffsetDateTime,
+    pub(crate) elapsed: Duration,
+}
+
+impl Now {
+    pub(crate) fn new(timezone: Timezone, start: Instant) -> Self {
+        Self {
+            time: timezone.now(),
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Append the time as written for `clock` to `buf`.
+    pub(crate) fn format_into(
+        self,
+        buf: &mut Vec<u8>,
+        cache: &mut TimeCache,
+        time_format: &TimeFormat,
+        clock: Clock,
+    ) -> Result<(), time::error::Format> {
+        match clock {
+            Clock::WallClock => cache.format_into(buf, time_format, self.time),
+            Clock::Elapsed => write_display(buf, Elapsed(self.elapsed)),
+            Clock::Both => {
+                cache.format_into(buf, time_format, self.time)?;
+                write_display(buf, format_args!(" {}", Elapsed(self.elapsed)))
+            }
+        }
+    }
+}
+
+/// Times formatted at the start of the current second, one per time format,
+/// so that logging many messages per second only has to render the sub-second
+/// digits of each. Custom formats and [`TimeFormat::UnixNanos`] are not cached.
+#[derive(Debug, Default)]
+pub(crate) struct TimeCache {
+    seconds: [CachedSecond; 5],
+}
+
+impl TimeCache {
+    /// Append `time` in `time_format` to `buf`.
+    pub(crate) fn format_into(
+        &mut self,
+        buf: &mut Vec<u8>,
+        time_format: &TimeFormat,
+        time: OffsetDateTime,
+    ) -> Result<(), time::error::Format> {
+        let (index, subsecond) = match time_format {
+            TimeFormat::Rfc2822 => (0, Subsecond::None),
+            TimeFormat::Rfc3339 => (1, Subsecond::Trimmed),
+            TimeFormat::Iso8601Millis => (2, Subsecond::Millis),
+            TimeFormat::Iso8601Micros => (3, Subsecond::Micros),
+            TimeFormat::UnixSeconds => (4, Subsecond::None),
+            TimeFormat::UnixNanos | TimeFormat::Custom(_) => {
+                return time_format.format_into(buf, time);
+            }
+        };
+        let cached = &mut self.seconds[index];
+        if cached.second != Some((time.unix_timestamp(), time.offset()))
+            && !cached.fill(time_format, subsecond, time)
+        {
+            return time_format.format_into(buf, time);
+        }
+        buf.extend_from_slice(&cached.text[..cached.fraction]);
+        subsecond.write(buf, time.nanosecond())?;
+        buf.extend_from_slice(&cached.text[cached.rest..]);
+        Ok(())
+    }
+}
+
+/// A time formatted at the start of its second.
+#[derive(Debug, Default)]
+struct CachedSecond {
+    second: Option<(i64, UtcOffset)>,
+    text: Vec<u8>,
+    // The sub-second part, if any, is `text[fraction..rest]`.
+    fraction: usize,
+    rest: usize,
+}
+
+impl CachedSecond {
+    /// Format the start of the second of `time`. Returns `false` if the
+    /// sub-second part cannot be located, leaving the cache empty.
+    fn fill(
+        &mut self,
+        time_format: &TimeFormat,
+        subsecond: Subsecond,
+        time: OffsetDateTime,
+    ) -> bool {
+        self.second = None;
+        self.text.clear();
+        let Ok(start) = time.replace_nanosecond(0) else {
+            return false;
+        };
+        if time_format.format_into(&mut self.text, start).is_err() {
+            return false;
+        }
+        (self.fraction, self.rest) = match subsecond {
+            Subsecond::None => (self.text.len(), self.text.len()),
+            // Whole seconds are written without a fraction, right after the
+            // four digit year, month, day, hour, minute and second.
+            Subsecond::Trimmed if self.text.len() >= RFC3339_SECONDS_END => {
+                (RFC3339_SECONDS_END, RFC3339_SECONDS_END)
+            }
+            Subsecond::Millis | Subsecond::Micros => {
+                let Some(dot) = self.text.iter().position(|&b| b == b'.') else {
+                    return false;
+                };
+                (dot, (dot + 1 + subsecond.digits()).min(self.text.len()))
+            }
+            Subsecond::Trimmed => return false,
+        };
+        self.second = Some((time.unix_timestamp(), time.offset()));
+        true
+    }
+}
+
+/// Length of `YYYY-MM-DDTHH:MM:SS`.
+const RFC3339_SECONDS_END: usize = 19;
+
+/// How the sub-second part of a time format is written.
+#[derive(Copy, Clone, Debug)]
+enum Subsecond {
+    /// Not at all.
+    None,
+    /// `.123`
+    Millis,
+    /// `.123456`
+    Micros,
+    /// Up to nine digits without trailing zeros, left out for whole seconds.
+    Trimmed,
+}
+
+impl Subsecond {
+    fn digits(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Millis => 3,
+            Self::Micros => 6,
+            Self::Trimmed => 9,
+        }
+    }
+
+    fn write(self, buf: &mut Vec<u8>, nanos: u32) -> Result<(), time::error::Format> {
+        match self {
+            Self::None => Ok(()),
+            Self::Millis => write_display(buf, format_args!(".{:03}", nanos / 1_000_000)),
+            Self::Micros => write_display(buf, format_args!(".{:06}", nanos / 1_000)),
+            Self::Trimmed if nanos == 0 => Ok(()),
+            Self::Trimmed => {
+                let (mut nanos, mut width) = (nanos, self.digits());
+                while nanos % 10 == 0 {
+                    nanos /= 10;
+                    width -= 1;
+                }
+                write_display(buf, format_args!(".{nanos:0width$}"))
+            }
+        }
+    }
+}
+
+fn write_display(buf: &mut Vec<u8>, value: impl fmt::Display) -> Result<(), time::error::Format> {
+    write!(buf, "{value}").map_err(time::error::Format::StdIo)
+}
+
+/// Writes a duration as `+12.345678s`.
+pub(crate) struct Elapsed(pub(crate) Duration);
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{}.{:06}s", self.0.as_secs(), self.0.subsec_micros())
+    }
+}
+
+/// Time zone log messages are timestamped in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Timezone {
+    /// The local time zone. Its UTC offset is determined once, when the first
+    /// logger is enabled, so it does not follow daylight saving time changes
+    /// while the program runs. If it cannot be determined, which is common for
+    /// multithreaded programs on some platforms, a warning is printed and UTC
+    /// is used.
+    #[default]
+    Local,
+    /// UTC.
+    Utc,
+    /// A fixed offset from UTC.
+    Fixed(UtcOffset),
+}
+
+impl Timezone {
+    pub(crate) fn offset(self) -> UtcOffset {
+        match self {
+            Self::Local => local_offset(),
+            Self::Utc => UtcOffset::UTC,
+            Self::Fixed(offset) => offset,
+        }
+    }
+
+    pub(crate) fn now(self) -> OffsetDateTime {
+        OffsetDateTime::now_utc().to_offset(self.offset())
+    }
+}
+
+/// The local UTC offset, determined on first use.
+pub(crate) fn local_offset() -> UtcOffset {
+    static LOCAL_OFFSET: OnceLock<UtcOffset> = OnceLock::new();
+    *LOCAL_OFFSET.get_or_init(|| {
+        UtcOffset::current_local_offset().unwrap_or_else(|e| {
+            eprintln!("could not determine the local UTC offset, using UTC: {e}");
+            UtcOffset::UTC
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CACHED: [TimeFormat; 5] = [
+        TimeFormat::Rfc2822,
+        TimeFormat::Rfc3339,
+        TimeFormat::Iso8601Millis,
+        TimeFormat::Iso8601Micros,
+        TimeFormat::UnixSeconds,
+    ];
+
+    fn direct(time_format: &TimeFormat, time: OffsetDateTime) -> String {
+        let mut buf = Vec::new();
+        time_format.format_into(&mut buf, time).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn cached(cache: &mut TimeCache, time_format: &TimeFormat, time: OffsetDateTime) -> String {
+        let mut buf = Vec::new();
+        cache.format_into(&mut buf, time_format, time).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn cache_matches_direct_formatting() {
+        let nanoseconds = [
+            0,
+            1,
+            10,
+            999,
+            1_000,
+            120_000,
+            100_000_000,
+            120_000_000,
+            123_000_000,
+            123_450_000,
+            123_456_000,
+            123_456_780,
+            123_456_789,
+            500_000_001,
+            999_999_999,
+        ];
+        let offsets = [
+            (0, 0),
+            (-3, -30),
+            (5, 45),
+            (14, 0),
+            (-12, 0),
+            (0, 30),
+            (0, -15),
+        ];
+        // Around the epoch, a leap day and the end of a year, one second apart.
+        let starts = [0, 951_782_398, 1_798_761_598];
+        // A single cache for everything, so entries are also replaced as the
+        // second or the offset changes.
+        let mut cache = TimeCache::default();
+        for start in starts {
+            for second in start..start + 4 {
+                for (hours, minutes) in offsets {
+                    let offset = UtcOffset::from_hms(hours, minutes, 0).unwrap();
+                    for nanosecond in nanoseconds {
+                        let time = OffsetDateTime::from_unix_timestamp(second)
+                            .unwrap()
+                            .replace_nanosecond(nanosecond)
+                            .unwrap()
+                            .to_offset(offset);
+                        for time_format in &CACHED {
+                            assert_eq!(
+                                cached(&mut cache, time_format, time),
+                                direct(time_format, time),
+                                "{time_format:?} at {time}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cache_keeps_offset_apart() {
+        let mut cache = TimeCache::default();
+        let time = OffsetDateTime::from_unix_timestamp(1_792_237_507).unwrap();
+        let shifted = time.to_offset(UtcOffset::from_hms(-3, -30, 0).unwrap());
+        for time_format in &CACHED {
+            let _ = cached(&mut cache, time_format, time);
+            assert_eq!(
+                cached(&mut cache, time_format, shifted),
+                direct(time_format, shifted)
+            );
+        }
+    }
+}